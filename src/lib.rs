@@ -48,7 +48,7 @@
 
 extern crate alloc;
 
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 use schema::Schemas;
 use sql_parse::{parse_statement, ParseOptions};
 pub use sql_parse::{Issue, Level};
@@ -58,6 +58,7 @@ mod type_binary_expression;
 mod type_delete;
 mod type_expression;
 mod type_function;
+mod type_group_by;
 mod type_insert_replace;
 mod type_reference;
 mod type_select;
@@ -81,6 +82,9 @@ pub struct TypeOptions {
     parse_options: ParseOptions,
     warn_unnamed_column_in_select: bool,
     warn_duplicate_column_in_select: bool,
+    default_namespace: Option<String>,
+    only_full_group_by: bool,
+    warn_join_without_foreign_key: bool,
 }
 
 impl TypeOptions {
@@ -89,6 +93,15 @@ impl TypeOptions {
         Default::default()
     }
 
+    /// Is namespace the one holding [Schemas::schemas]
+    pub(crate) fn is_default_namespace(&self, namespace: &str) -> bool {
+        match &self.default_namespace {
+            Some(v) => v == namespace,
+            // Without a search path postgresql puts tables in the public schema
+            None => self.parse_options.get_dialect().is_postgresql() && namespace == "public",
+        }
+    }
+
     /// Change what sql dialect is used
     pub fn dialect(self, dialect: SQLDialect) -> Self {
         Self {
@@ -148,6 +161,33 @@ impl TypeOptions {
             ..self
         }
     }
+
+    /// Require selected and HAVING columns to be grouped or aggregated,
+    /// like ONLY_FULL_GROUP_BY in MariaDB
+    pub fn only_full_group_by(self, only_full_group_by: bool) -> Self {
+        Self {
+            only_full_group_by,
+            ..self
+        }
+    }
+
+    /// Should we warn about joins on columns of different types that are not
+    /// related by a foreign key
+    pub fn warn_join_without_foreign_key(self, warn_join_without_foreign_key: bool) -> Self {
+        Self {
+            warn_join_without_foreign_key,
+            ..self
+        }
+    }
+
+    /// Name of the database or schema holding [Schemas::schemas], names qualified
+    /// by any other namespace are looked up in [Schemas::namespaces]
+    pub fn default_namespace(self, default_namespace: impl Into<String>) -> Self {
+        Self {
+            default_namespace: Some(default_namespace.into()),
+            ..self
+        }
+    }
 }
 
 /// Key of argument
@@ -211,14 +251,14 @@ pub fn type_statement<'a>(
             schemas,
             issues,
             reference_types: Vec::new(),
-            arg_types: Default::default(),
+            arg_uses: Default::default(),
             options,
         };
         let t = type_statement::type_statement(&mut typer, &stmt);
-        let arguments = typer.arg_types;
+        let arguments = typer.arg_types();
         match t {
             type_statement::InnerStatementType::Select(s) => StatementType::Select {
-                columns: s.columns,
+                columns: s.into_result_columns(),
                 arguments,
             },
             type_statement::InnerStatementType::Delete => StatementType::Delete { arguments },
@@ -228,12 +268,12 @@ pub fn type_statement<'a>(
             } => StatementType::Insert {
                 yield_autoincrement: auto_increment_id,
                 arguments,
-                returning: returning.map(|r| r.columns),
+                returning: returning.map(|r| r.into_result_columns()),
             },
             type_statement::InnerStatementType::Update => StatementType::Update { arguments },
             type_statement::InnerStatementType::Replace { returning } => StatementType::Replace {
                 arguments,
-                returning: returning.map(|r| r.columns),
+                returning: returning.map(|r| r.into_result_columns()),
             },
             type_statement::InnerStatementType::Invalid => StatementType::Invalid,
         }
@@ -250,14 +290,15 @@ mod tests {
         files::SimpleFiles,
         term::{
             self,
-            termcolor::{ColorChoice, StandardStream},
+            termcolor::{Buffer, ColorChoice, StandardStream, WriteColor},
         },
     };
     use sql_parse::{Issue, Level, SQLArguments, SQLDialect};
 
     use crate::{
-        schema::parse_schemas, type_statement, ArgumentKey, AutoIncrementId, BaseType, FullType,
-        SelectTypeColumn, StatementType, Type, TypeOptions,
+        schema::{parse_schemas, ForeignKeyAction},
+        type_statement, ArgumentKey, AutoIncrementId, BaseType, FullType, SelectTypeColumn,
+        StatementType, Type, TypeOptions,
     };
 
     struct N<'a>(Option<&'a str>);
@@ -271,26 +312,78 @@ mod tests {
         }
     }
 
-    fn check_no_errors(name: &str, src: &str, issues: &[Issue], errors: &mut usize) {
+    /// Render issue in the source src to writer
+    fn render_issue(
+        name: &str,
+        src: &str,
+        issue: &Issue,
+        writer: &mut dyn WriteColor,
+    ) -> Result<(), codespan_reporting::files::Error> {
         let mut files = SimpleFiles::new();
         let file_id = files.add(name, &src);
-        let writer = StandardStream::stderr(ColorChoice::Always);
         let config = codespan_reporting::term::Config::default();
+        let mut labels = vec![Label::primary(file_id, issue.span.clone())];
+        for (message, span) in &issue.fragments {
+            labels.push(Label::secondary(file_id, span.clone()).with_message(message));
+        }
+        let d = match issue.level {
+            Level::Error => Diagnostic::error(),
+            Level::Warning => Diagnostic::warning(),
+        };
+        let d = d.with_message(&issue.message).with_labels(labels);
+        term::emit(writer, &config, &files, &d)
+    }
+
+    fn check_no_errors(name: &str, src: &str, issues: &[Issue], errors: &mut usize) {
+        let writer = StandardStream::stderr(ColorChoice::Always);
         for issue in issues {
-            let mut labels = vec![Label::primary(file_id, issue.span.clone())];
-            for (message, span) in &issue.fragments {
-                labels.push(Label::secondary(file_id, span.clone()).with_message(message));
+            if issue.level == Level::Error {
+                *errors += 1;
             }
-            let d = match issue.level {
-                Level::Error => {
-                    *errors += 1;
-                    Diagnostic::error()
-                }
-                Level::Warning => Diagnostic::warning(),
-            };
-            let d = d.with_message(&issue.message).with_labels(labels);
-            term::emit(&mut writer.lock(), &config, &files, &d).unwrap();
+            render_issue(name, src, issue, &mut writer.lock()).unwrap();
+        }
+    }
+
+    /// Check that an issue with the given level and message was reported on the text at
+    fn check_issue(
+        name: &str,
+        src: &str,
+        issues: &[Issue],
+        level: Level,
+        message: &str,
+        at: &str,
+        errors: &mut usize,
+    ) {
+        if let Some(issue) = issues.iter().find(|i| {
+            i.level == level && i.message == message && src.get(i.span.clone()) == Some(at)
+        }) {
+            // All spans of the issue must be in the source it is reported against
+            if issue
+                .fragments
+                .iter()
+                .any(|(_, s)| src.get(s.clone()).is_none())
+            {
+                println!("{}: Fragment of '{}' outside of the source", name, message);
+                *errors += 1;
+            } else if let Err(e) = render_issue(name, src, issue, &mut Buffer::no_color()) {
+                println!("{}: Cannot render '{}': {}", name, message, e);
+                *errors += 1;
+            }
+            return;
+        }
+        println!(
+            "{}: Expected {:?} '{}' at '{}' got:",
+            name, level, message, at
+        );
+        for i in issues {
+            println!(
+                "    {:?} '{}' at '{}'",
+                i.level,
+                i.message,
+                src.get(i.span.clone()).unwrap_or("")
+            );
         }
+        *errors += 1;
     }
 
     fn str_to_type(t: &str) -> FullType<'static> {
@@ -321,6 +414,17 @@ mod tests {
             "str" => BaseType::String.into(),
             "bytes" => BaseType::Bytes.into(),
             "dt" => BaseType::DateTime.into(),
+            "date" => BaseType::Date.into(),
+            "ts" => BaseType::TimeStamp.into(),
+            "dec" => BaseType::Decimal.into(),
+            t if t.starts_with("dec") => {
+                // Precise decimals are written dec<precision>.<scale>
+                let (precision, scale) = t[3..].split_once('.').expect("decimal scale");
+                Type::Decimal {
+                    precision: precision.parse().expect("decimal precision"),
+                    scale: scale.parse().expect("decimal scale"),
+                }
+            }
             "json" => Type::JSON,
             _ => panic!("Unknown type {}", t),
         };
@@ -464,6 +568,30 @@ mod tests {
         CREATE TABLE `t4` (
             `id` int(11) NOT NULL AUTO_INCREMENT,
             `dt` datetime NOT NULL);
+
+        CREATE TABLE `t5` (
+            `id` int(11) NOT NULL AUTO_INCREMENT,
+            `price` numeric(10, 2) NOT NULL,
+            `discount` numeric(10, 2));
+
+        ALTER TABLE `t2`
+            ADD PRIMARY KEY (`id`),
+            ADD KEY `t1_id` (`t1_id`),
+            ADD CONSTRAINT `t2_t1` FOREIGN KEY (`t1_id`) REFERENCES `t1` (`id`) ON DELETE CASCADE;
+
+        ALTER TABLE `t1`
+            ADD FOREIGN KEY (`ci64`) REFERENCES `t2` (`id`);
+
+        CREATE UNIQUE INDEX `t4_dt` ON `t4` (`dt`);
+
+        CREATE VIEW `v1` AS
+            SELECT `t2`.`id` AS `id`, `t1`.`ctext` AS `ctext`, `t3`.`text` AS `text`
+            FROM `t2`
+            JOIN `t1` ON `t1`.`id` = `t2`.`t1_id`
+            LEFT JOIN `t3` ON `t3`.`id` = `t2`.`id`;
+
+        CREATE VIEW `v2` AS
+            SELECT `id`, `text` FROM `v1` WHERE `text` IS NOT NULL;
         ";
 
         let options = TypeOptions::new().dialect(SQLDialect::MariaDB);
@@ -492,7 +620,7 @@ mod tests {
                 check_arguments(
                     name,
                     &arguments,
-                    "b,i,i,i,i,i,i,i,i,str,bytes,f,f",
+                    "b,u8,u16,u32,u64,i8,i16,i32,i64,str,bytes,f32,f64",
                     &mut errors,
                 );
                 check_columns(
@@ -574,7 +702,7 @@ mod tests {
             let q = type_statement(&schema, src, &mut issues, &options);
             check_no_errors(name, src, &issues, &mut errors);
             if let StatementType::Delete { arguments } = q {
-                check_arguments(name, &arguments, "i", &mut errors);
+                check_arguments(name, &arguments, "i32", &mut errors);
             } else {
                 println!("{} should be delete", name);
                 errors += 1;
@@ -657,7 +785,7 @@ mod tests {
             let q = type_statement(&schema, src, &mut issues, &options);
             check_no_errors(name, src, &issues, &mut errors);
             if let StatementType::Select { arguments, columns } = q {
-                check_arguments(name, &arguments, "i", &mut errors);
+                check_arguments(name, &arguments, "i32", &mut errors);
                 check_columns(name, &columns, "cc:dt!", &mut errors);
             } else {
                 println!("{} should be select", name);
@@ -752,7 +880,7 @@ mod tests {
             let q = type_statement(&schema, src, &mut issues, &options);
             check_no_errors(name, src, &issues, &mut errors);
             if let StatementType::Select { arguments, columns } = q {
-                check_arguments(name, &arguments, "i[]", &mut errors);
+                check_arguments(name, &arguments, "i32[]", &mut errors);
                 check_columns(name, &columns, "id:i32!", &mut errors);
             } else {
                 println!("{} should be select", name);
@@ -857,71 +985,1194 @@ mod tests {
             }
         }
 
-        if errors != 0 {
-            panic!("{} errors in test", errors);
+        {
+            issues.clear();
+            let name = "q18";
+            let src = "SELECT `id`, `ctext`, `text` FROM `v1` WHERE `id` = ?";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "i32", &mut errors);
+                check_columns(name, &columns, "id:i32!,ctext:str!,text:str", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
         }
-    }
 
-    #[test]
-    fn postgresql() {
-        let schema_src = "
-        BEGIN;
+        {
+            issues.clear();
+            let name = "q19";
+            let src = "SELECT * FROM `v2`";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "", &mut errors);
+                check_columns(name, &columns, "id:i32!,text:str!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
 
-        DO $$ BEGIN
-            CREATE TYPE my_enum AS ENUM (
-            'V1',
-            'V2',
-            'V3'
-        );
-        EXCEPTION
-            WHEN duplicate_object THEN null;
-        END $$;
+        {
+            issues.clear();
+            let name = "q20";
+            let src = "SELECT `price` - `discount` AS `p`, `price` * 2 AS `d`, SUM(`price`) AS `s`,
+                AVG(`price`) AS `a`, CAST(`id` AS NUMERIC(10, 2)) AS `c`
+                FROM `t5` WHERE `price` > ? AND `discount` < 10";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "dec10.2", &mut errors);
+                check_columns(
+                    name,
+                    &columns,
+                    "p:dec11.2!,d:dec10.2!,s:dec32.2,a:dec14.6,c:dec10.2!",
+                    &mut errors,
+                );
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
 
-        CREATE TABLE IF NOT EXISTS t1 (
-            id bigint NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
-            path text NOT NULL UNIQUE,
-            v my_enum NOT NULL,
-            time timestamptz NOT NULL DEFAULT now(),
-            old_id bigint,
-            CONSTRAINT t1__old
-            FOREIGN KEY(old_id) 
-            REFERENCES t1(id)
-            ON DELETE SET NULL
-        );
+        {
+            issues.clear();
+            let name = "q21";
+            let src =
+                "SELECT CASE WHEN `ci32` IS NULL THEN ? WHEN `cbool` THEN `cu8` ELSE 7 END AS `a`,
+                CASE `ctext` WHEN 'x' THEN `ctext` WHEN ? THEN 'y' END AS `b`,
+                CASE WHEN `cbool` THEN `cu8` ELSE `cu16` END AS `c`,
+                CASE WHEN `cbool` THEN ? WHEN `ci32` IS NULL THEN `cu8` ELSE `ci64` END AS `d`
+                FROM `t1`";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "u8,str,i64", &mut errors);
+                check_columns(name, &columns, "a:u8,b:str,c:u16!,d:i64", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
 
-        CREATE TABLE IF NOT EXISTS t2 (
-            id bigint NOT NULL PRIMARY KEY
-        );
+        {
+            issues.clear();
+            let name = "q22";
+            let src = "SELECT CASE WHEN `cbool` THEN `ctext` ELSE `cu8` END AS `a` FROM `t1`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Incompatible types in case",
+                "CASE",
+                &mut errors,
+            );
 
-        COMMIT;
-        ";
+            issues.clear();
+            let src = "SELECT CASE `cu8` WHEN `cbytes` THEN 1 END AS `a` FROM `t1`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Type error in comparison",
+                "WHEN",
+                &mut errors,
+            );
+        }
 
-        let options = TypeOptions::new().dialect(SQLDialect::PostgreSQL);
-        let mut issues = Vec::new();
-        let schema = parse_schemas(schema_src, &mut issues, &options);
-        let mut errors = 0;
-        check_no_errors("schema", schema_src, &issues, &mut errors);
+        {
+            issues.clear();
+            let name = "q23";
+            // Without a numeric context a negated argument is typed as a float
+            let src =
+                "SELECT -`cu8` AS `a`, -`ci32` AS `b`, !`cbool` AS `c`, BINARY `ctext` AS `d`,
+                -`cf64` AS `f`, -? AS `g` FROM `t1`
+                WHERE `cbool` = !? AND `ci8` > -?";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "f,b,i8", &mut errors);
+                check_columns(
+                    name,
+                    &columns,
+                    "a:i16!,b:i32,c:b!,d:bytes!,f:f64,g:f",
+                    &mut errors,
+                );
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
 
-        issues.clear();
-        let options = TypeOptions::new()
-            .dialect(SQLDialect::PostgreSQL)
-            .arguments(SQLArguments::Dollar);
+        {
+            issues.clear();
+            let name = "q24";
+            let src = "SELECT -`ctext` AS `a` FROM `t1`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Expected numeric type",
+                "-",
+                &mut errors,
+            );
+        }
 
         {
-            let name = "q1";
+            issues.clear();
+            let name = "q25";
             let src =
-                "INSERT INTO t2 (id) SELECT id FROM t1 WHERE path=$1 ON CONFLICT (id) DO NOTHING RETURNING id";
+                "SELECT *, `t3`.`id` AS `x` FROM `t3` RIGHT JOIN `t4` USING `id` WHERE `id` = ?";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "i32", &mut errors);
+                check_columns(name, &columns, "id:i32!,text:str,dt:dt!,x:i32", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q26";
+            let src = "SELECT * FROM `t3` JOIN `t4` USING `text`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Unknown column on right side of join",
+                "`text`",
+                &mut errors,
+            );
+
+            issues.clear();
+            let src = "SELECT * FROM `t3` JOIN `t4` USING `id`, `id`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Column given multiple times",
+                "`id`",
+                &mut errors,
+            );
+        }
 
+        {
+            issues.clear();
+            let name = "q27";
+            let src = "SELECT STRAIGHT_JOIN * FROM `t3` NATURAL LEFT JOIN `t4` WHERE `text` = ?";
             let q = type_statement(&schema, src, &mut issues, &options);
             check_no_errors(name, src, &issues, &mut errors);
-            if let StatementType::Insert {
-                arguments,
-                returning,
-                ..
-            } = q
-            {
+            if let StatementType::Select { arguments, columns } = q {
                 check_arguments(name, &arguments, "str", &mut errors);
-                check_columns(name, &returning.expect("Returning"), "id:i64!", &mut errors);
+                check_columns(name, &columns, "id:i32!,text:str!,dt:dt", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let options = options.clone().default_namespace("db");
+
+            let name = "q28";
+            let src = "SELECT `db`.`t3`.`id`, `t4`.`dt` FROM `db`.`t3`
+                JOIN `t4` ON `db`.`t4`.`id` = `t3`.`id` WHERE `db`.`t3`.`text` = ?";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "str", &mut errors);
+                check_columns(name, &columns, "id:i32!,dt:dt!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+
+            let name = "q29";
+            let src = "UPDATE `db`.`t3` SET `db`.`t3`.`text` = ? WHERE `id` = ?";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Update { arguments } = q {
+                check_arguments(name, &arguments, "str,i32", &mut errors);
+            } else {
+                println!("{} should be update", name);
+                errors += 1;
+            }
+
+            let name = "q30";
+            let src = "SELECT `id` FROM `nope`.`t3`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Unknown database or schema",
+                "`nope`",
+                &mut errors,
+            );
+
+            issues.clear();
+            let src = "SELECT `db`.`t4`.`id` FROM `t3` AS `t4`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Unknown table in db",
+                "`t4`",
+                &mut errors,
+            );
+        }
+
+        {
+            issues.clear();
+            let options = options.clone().default_namespace("db");
+            let mut schema = parse_schemas(schema_src, &mut issues, &options);
+            let other_src = "CREATE TABLE `u1` (
+                `id` int(11) NOT NULL,
+                `name` varchar(100) NOT NULL,
+                `note` text);";
+            let other = parse_schemas(other_src, &mut issues, &options);
+            schema.namespaces.insert("other", other.schemas);
+
+            let name = "q30.1";
+            let src = "SELECT `other`.`u1`.`name`, `t3`.`text` FROM `other`.`u1`
+                JOIN `db`.`t3` ON `t3`.`id` = `u1`.`id` WHERE `other`.`u1`.`note` = ?";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "str", &mut errors);
+                check_columns(name, &columns, "name:str!,text:str", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+
+            let name = "q30.2";
+            let src = "INSERT INTO `other`.`u1` (`id`, `name`) VALUES (?, ?)";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Insert { arguments, .. } = q {
+                check_arguments(name, &arguments, "i32!,str!", &mut errors);
+            } else {
+                println!("{} should be insert", name);
+                errors += 1;
+            }
+
+            let name = "q30.3";
+            let src = "SELECT `id` FROM `other`.`t3`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Unknown table or view",
+                "`t3`",
+                &mut errors,
+            );
+        }
+
+        {
+            let options = options.clone().only_full_group_by(true);
+            issues.clear();
+            let name = "q31";
+            let src = "SELECT `t1_id`, COUNT(*) AS `c`, MAX(`id`) AS `m` FROM `t2`
+                GROUP BY `t2`.`t1_id` HAVING `c` > ? AND MIN(`id`) > 0";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "i64", &mut errors);
+                check_columns(name, &columns, "t1_id:i32!,c:i64!,m:i32", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+
+            for (name, src, message, at) in [
+                (
+                    "q32",
+                    "SELECT `id`, COUNT(*) AS `c` FROM `t2` GROUP BY `t1_id`",
+                    "'id' is not in GROUP BY nor in an aggregate",
+                    "`id`",
+                ),
+                (
+                    "q33",
+                    "SELECT `id` FROM `t2` WHERE SUM(`id`) > 1",
+                    "Aggregates are not allowed in WHERE",
+                    "SUM(`id`",
+                ),
+                (
+                    "q34",
+                    "SELECT `t1_id` FROM `t2` GROUP BY `t1_id` HAVING `id` > 1",
+                    "'id' is not in GROUP BY nor in an aggregate",
+                    "`id`",
+                ),
+                (
+                    "q34.1",
+                    "SELECT `t1_id` FROM `t2` GROUP BY 2",
+                    "Unknown column position in GROUP BY",
+                    "2",
+                ),
+            ] {
+                issues.clear();
+                type_statement(&schema, src, &mut issues, &options);
+                check_issue(name, src, &issues, Level::Error, message, at, &mut errors);
+            }
+
+            for (name, src) in [
+                (
+                    "q34.2",
+                    "SELECT `t1_id`, COUNT(*) AS `c` FROM `t2` GROUP BY 1",
+                ),
+                (
+                    "q34.3",
+                    "SELECT `t1_id` + 1 AS `a`, MAX(`id`) AS `m` FROM `t2`
+                    GROUP BY `t2`.`t1_id` + 1 HAVING `t1_id` + 1 > 2",
+                ),
+            ] {
+                issues.clear();
+                type_statement(&schema, src, &mut issues, &options);
+                check_no_errors(name, src, &issues, &mut errors);
+            }
+        }
+
+        {
+            let t2 = &schema.schemas["t2"];
+            let pk = t2.primary_key().map(|i| i.columns.as_slice());
+            if pk != Some(&["id"][..]) || t2.indexes.len() != 2 {
+                println!(
+                    "schema: Expected primary key and index on t2 got {:?}",
+                    t2.indexes
+                );
+                errors += 1;
+            }
+            if !schema.schemas["t4"].is_unique(&["dt", "id"]) || t2.is_unique(&["t1_id"]) {
+                println!("schema: Wrong uniqueness of t4.dt or t2.t1_id");
+                errors += 1;
+            }
+
+            issues.clear();
+            let options = options.clone().only_full_group_by(true);
+            let name = "q35";
+            let src = "SELECT `t2`.`id`, `t2`.`t1_id`, COUNT(*) AS `c` FROM `t2`
+                JOIN `t1` ON `t1`.`id` = `t2`.`t1_id` GROUP BY `t2`.`id`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+        }
+
+        {
+            let fk = &schema.schemas["t2"].foreign_keys;
+            if fk.len() != 1
+                || fk[0].name != Some("t2_t1")
+                || fk[0].references_table != "t1"
+                || fk[0].on_delete != ForeignKeyAction::Cascade
+                || fk[0].on_update != ForeignKeyAction::NoAction
+            {
+                println!("schema: Wrong foreign keys on t2 {:?}", fk);
+                errors += 1;
+            }
+
+            issues.clear();
+            let src = "CREATE TABLE `a` (`id` int NOT NULL, `b_id` text NOT NULL);
+                CREATE TABLE `b` (`id` int NOT NULL);
+                ALTER TABLE `a` ADD FOREIGN KEY (`b_id`) REFERENCES `b` (`id`);
+                ALTER TABLE `a` ADD FOREIGN KEY (`id`) REFERENCES `b` (`nope`);";
+            parse_schemas(src, &mut issues, &options);
+            let name = "bad foreign keys";
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Incompatible types in foreign key",
+                "ADD FOREIGN KEY (`b_id`) REFERENCES `b` (`id`",
+                &mut errors,
+            );
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "No such column in table",
+                "`nope`",
+                &mut errors,
+            );
+
+            issues.clear();
+            let src = "CREATE TABLE `a` (`id` int NOT NULL);
+                CREATE TABLE `b` (`id` int NOT NULL);
+                ALTER TABLE `a` ADD FOREIGN KEY () REFERENCES `b` (`id`);";
+            parse_schemas(src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Foreign key has a different number of columns than referenced",
+                "ADD FOREIGN KEY () REFERENCES `b` (`id`",
+                &mut errors,
+            );
+
+            let options = options.clone().warn_join_without_foreign_key(true);
+            issues.clear();
+            let name = "q36";
+            let src = "SELECT `t1`.`id` FROM `t1` JOIN `t2` ON `t1`.`ci64` = `t2`.`id`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if !issues.is_empty() {
+                println!("{} should not warn", name);
+                errors += 1;
+            }
+
+            let name = "q37";
+            let src = "SELECT `t1`.`id` FROM `t1` JOIN `t2` ON `t1`.`cu32` = `t2`.`id`";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Warning,
+                "Join on columns of different types without a foreign key",
+                "=",
+                &mut errors,
+            );
+        }
+
+        {
+            issues.clear();
+            let name = "q38";
+            let src = "INSERT INTO `t5` SET `price` = ?";
+            type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+
+            for (name, src, missing, at) in [
+                ("q39", "INSERT INTO `t2` (`id`) VALUES (?)", "t1_id", "`t2`"),
+                (
+                    "q40",
+                    "REPLACE INTO `t5` SET `discount` = ?",
+                    "price",
+                    "`t5`",
+                ),
+            ] {
+                issues.clear();
+                type_statement(&schema, src, &mut issues, &options);
+                check_issue(
+                    name,
+                    src,
+                    &issues,
+                    Level::Error,
+                    &format!(
+                        "No value given for NOT NULL column without default: {}",
+                        missing
+                    ),
+                    at,
+                    &mut errors,
+                );
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q41";
+            let src = "INSERT INTO `t3` VALUES (?, ?)";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Insert { arguments, .. } = q {
+                check_arguments(name, &arguments, "i32!,str", &mut errors);
+            } else {
+                println!("{} should be insert", name);
+                errors += 1;
+            }
+
+            for (name, src, message, at) in [
+                (
+                    "q42",
+                    "INSERT INTO `t3` VALUES (?)",
+                    "Expected 2 values got 1",
+                    "?",
+                ),
+                (
+                    "q43",
+                    "INSERT INTO `t3` SELECT `id` FROM `t2`",
+                    "Missing column in select",
+                    "`t3`",
+                ),
+            ] {
+                issues.clear();
+                type_statement(&schema, src, &mut issues, &options);
+                check_issue(name, src, &issues, Level::Error, message, at, &mut errors);
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q44";
+            let src = "SELECT `cu32` + 1 AS `a`, `ci8` * `ci16` AS `b`, `cu8` / 2 AS `c`,
+                `cf32` + `cf32` AS `d` FROM `t1` WHERE `cu64` = ?";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "u64", &mut errors);
+                check_columns(name, &columns, "a:u64!,b:i64,c:dec7.4!,d:f64", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q45";
+            let src = "SELECT `id`, UNIX_TIMESTAMP('2024-01-01') AS `t` FROM `t4`
+                WHERE `dt` > '2024-01-01' AND '2024-01-01 10:00:00' <= `dt`
+                AND `dt` IN ('2024-02-01', ?)";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "dt", &mut errors);
+                check_columns(name, &columns, "id:i32!,t:i64!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+
+            issues.clear();
+            let name = "q46";
+            let src = "SELECT `id` FROM `t4` WHERE `dt` > '2024-13'";
+            type_statement(&schema, src, &mut issues, &options);
+            if !issues.iter().any(|i| i.level == Level::Warning) {
+                println!("{} should warn", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q47";
+            let src = "SELECT COALESCE(?, `id`) AS `a`, IF(?, ?, `ctext`) AS `b`, ? + 1 AS `c`
+                FROM `t1` WHERE `cu32` = IFNULL(?, 1) AND `ci16` = ? + 1";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "i32,b,str,i,u32,i16", &mut errors);
+                check_columns(name, &columns, "a:i32!,b:str,c:i", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q48";
+            let src = "SELECT `cf32` + 1 AS `a`, `price` / 3 AS `b`, `price` * `ci32` AS `c`,
+                `price` * `price` * `price` * `price` * `price` * `price` * `price` AS `d`
+                FROM `t1` JOIN `t5` ON `t5`.`id` = `t1`.`id` WHERE `cf32` > 1 AND `cf64` < ?";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "f64", &mut errors);
+                check_columns(
+                    name,
+                    &columns,
+                    "a:f64!,b:dec14.6!,c:dec20.2,d:dec65.14!",
+                    &mut errors,
+                );
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q49";
+            let src = "INSERT INTO `t5` (`price`, `discount`) SELECT ?, ?";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Insert { arguments, .. } = q {
+                check_arguments(name, &arguments, "dec10.2!,dec10.2", &mut errors);
+            } else {
+                println!("{} should be insert", name);
+                errors += 1;
+            }
+        }
+
+        for (name, src) in [
+            ("q50", "SELECT `id` FROM `t1` WHERE `id` = (SELECT ?)"),
+            (
+                "q51",
+                "SELECT `id` FROM `t1` WHERE `id` IN (SELECT ? FROM `t2`)",
+            ),
+        ] {
+            issues.clear();
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "i32", &mut errors);
+                check_columns(name, &columns, "id:i32!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q52";
+            let src = "SELECT COALESCE(?, `cu8`, `ci64`) AS `a`, COALESCE(`ci8`, ?, `cu16`) AS `b`
+                FROM `t1`";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "i64,i32", &mut errors);
+                check_columns(name, &columns, "a:i64!,b:i32!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q53";
+            let src = "SELECT SUM(`cu8`) AS `a`, SUM(`id`) AS `b`, SUM(`cu64`) AS `c`,
+                SUM(`cf32`) AS `d` FROM `t1`";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { columns, .. } = q {
+                check_columns(
+                    name,
+                    &columns,
+                    "a:dec25.0,b:dec32.0,c:dec42.0,d:f64",
+                    &mut errors,
+                );
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q54";
+            let src =
+                "SELECT `dt` - `dt` AS `a`, `dt` + 1 AS `b`, CURDATE() - `id` AS `c` FROM `t4`";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { columns, .. } = q {
+                check_columns(name, &columns, "a:i64!,b:i64!,c:i64!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        if errors != 0 {
+            panic!("{} errors in test", errors);
+        }
+    }
+
+    #[test]
+    fn postgresql() {
+        let schema_src = "
+        BEGIN;
+
+        DO $$ BEGIN
+            CREATE TYPE my_enum AS ENUM (
+            'V1',
+            'V2',
+            'V3'
+        );
+        EXCEPTION
+            WHEN duplicate_object THEN null;
+        END $$;
+
+        -- Running the idempotent definition again is fine
+        DO $$ BEGIN
+            CREATE TYPE my_enum AS ENUM ('V1', 'V2', 'V3');
+        EXCEPTION
+            WHEN duplicate_object THEN null;
+        END $$;
+
+        CREATE TABLE IF NOT EXISTS t1 (
+            id bigint NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+            path text NOT NULL UNIQUE,
+            v my_enum NOT NULL,
+            time timestamptz NOT NULL DEFAULT now(),
+            old_id bigint,
+            CONSTRAINT t1__old
+            FOREIGN KEY(old_id) 
+            REFERENCES t1(id)
+            ON DELETE SET NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS t2 (
+            id bigint NOT NULL PRIMARY KEY
+        );
+
+        CREATE TABLE t3 (
+            n numeric(2, 5) NOT NULL,
+            m numeric(10, 2) NOT NULL
+        );
+
+        CREATE TABLE t4 (
+            id serial PRIMARY KEY,
+            name text NOT NULL
+        );
+
+        CREATE UNIQUE INDEX t1_old_id ON t1 (old_id) WHERE old_id IS NOT NULL;
+
+        CREATE FUNCTION add_path(IN a bigint, IN b text) RETURNS text
+            AS $$ SELECT b $$ LANGUAGE sql;
+
+        SET search_path = other;
+
+        CREATE TABLE u1 (
+            id int NOT NULL,
+            name text NOT NULL
+        );
+
+        CREATE TABLE t1 (
+            id int NOT NULL
+        );
+
+        SET search_path = public;
+
+        COMMIT;
+        ";
+
+        let options = TypeOptions::new().dialect(SQLDialect::PostgreSQL);
+        let mut issues = Vec::new();
+        let schema = parse_schemas(schema_src, &mut issues, &options);
+        let mut errors = 0;
+        check_no_errors("schema", schema_src, &issues, &mut errors);
+
+        issues.clear();
+        let options = TypeOptions::new()
+            .dialect(SQLDialect::PostgreSQL)
+            .arguments(SQLArguments::Dollar);
+
+        {
+            let name = "q1";
+            let src =
+                "INSERT INTO t2 (id) SELECT id FROM t1 WHERE path=$1 ON CONFLICT (id) DO NOTHING RETURNING id";
+
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Insert {
+                arguments,
+                returning,
+                ..
+            } = q
+            {
+                check_arguments(name, &arguments, "str", &mut errors);
+                check_columns(name, &returning.expect("Returning"), "id:i64!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q2";
+            let src = "INSERT INTO t1 (path, v) VALUES ($1, 'V1') ON CONFLICT (path) DO NOTHING";
+            type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+
+            // The unique index on old_id is partial
+            let name = "q3";
+            let src = "INSERT INTO t1 (path, v) VALUES ($1, 'V1') ON CONFLICT (old_id) DO NOTHING";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Warning,
+                "No unique key or constraint on column for ON CONFLICT",
+                "old_id",
+                &mut errors,
+            );
+        }
+
+        {
+            let t1 = &schema.schemas["t1"];
+            let id = t1.get_column("id").expect("id");
+            let time = t1.get_column("time").expect("time");
+            if !id.generated || id.default || time.generated || !time.default {
+                println!("schema: Wrong default or generated flags on t1");
+                errors += 1;
+            }
+
+            for (name, src) in [
+                ("q4", "INSERT INTO t1 (id, path, v) VALUES ($1, $2, 'V1')"),
+                ("q5", "UPDATE t1 SET id = $1 WHERE path = $2"),
+            ] {
+                issues.clear();
+                type_statement(&schema, src, &mut issues, &options);
+                check_issue(
+                    name,
+                    src,
+                    &issues,
+                    Level::Error,
+                    "Cannot write to generated column",
+                    "id",
+                    &mut errors,
+                );
+            }
+        }
+
+        {
+            issues.clear();
+            let src = "CREATE TABLE a (id int NOT NULL);
+                ALTER TABLE a ADD COLUMN IF NOT EXISTS id int NOT NULL;
+                ALTER TABLE a ADD COLUMN id text;";
+            let schema = parse_schemas(src, &mut issues, &options);
+            check_issue(
+                "duplicate columns",
+                src,
+                &issues,
+                Level::Error,
+                "Column already defined",
+                "id",
+                &mut errors,
+            );
+            if issues.iter().filter(|i| i.level == Level::Error).count() != 1 {
+                println!("duplicate columns: Expected one error");
+                errors += 1;
+            }
+            if schema.schemas.get("a").map(|s| s.columns.len()) != Some(1) {
+                println!("duplicate columns: Expected one column");
+                errors += 1;
+            }
+        }
+
+        {
+            let v = schema
+                .schemas
+                .get("t1")
+                .and_then(|t| t.get_column("v"))
+                .expect("v");
+            if v.type_.t.to_string() != "enum('V1', 'V2', 'V3')" {
+                println!("schema: Wrong type of v {}", v.type_.t);
+                errors += 1;
+            }
+
+            issues.clear();
+            let name = "q6";
+            let src = "SELECT id FROM t1 WHERE v = 'V2'";
+            type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+
+            for (name, src, value) in [
+                ("q7", "SELECT id FROM t1 WHERE v = 'V4'", "'V4'"),
+                ("q8", "INSERT INTO t1 (path, v) VALUES ($1, 'V9')", "'V9'"),
+                ("q9", "UPDATE t1 SET v = 'v1' WHERE id = $1", "'v1'"),
+            ] {
+                issues.clear();
+                type_statement(&schema, src, &mut issues, &options);
+                check_issue(
+                    name,
+                    src,
+                    &issues,
+                    Level::Error,
+                    &format!("{} is not a value of enum('V1', 'V2', 'V3')", value),
+                    value,
+                    &mut errors,
+                );
+            }
+
+            issues.clear();
+            let name = "bad types";
+            let src = "CREATE TYPE e AS ENUM ('a');
+                CREATE TYPE e AS ENUM ('b');
+                DO $$ BEGIN
+                    CREATE TYPE f AS ENUM ('a');
+                    SELECT 1;
+                EXCEPTION
+                    WHEN others THEN null;
+                END $$;
+                CREATE TABLE a (x f, y nope);";
+            let schema = parse_schemas(src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Type already defined",
+                "e",
+                &mut errors,
+            );
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Warning,
+                "Statement in DO block is ignored",
+                "SELECT 1",
+                &mut errors,
+            );
+            let a = schema.schemas.get("a");
+            let x = a.and_then(|t| t.get_column("x"));
+            if x.map(|c| c.type_.t.to_string()).as_deref() != Some("enum('a')") {
+                println!("{}: Wrong type of x", name);
+                errors += 1;
+            }
+            // Unknown named types are treated as strings
+            let y = a.and_then(|t| t.get_column("y"));
+            if y.map(|c| c.type_.t.clone()) != Some(BaseType::String.into()) {
+                println!("{}: Wrong type of y", name);
+                errors += 1;
+            }
+
+            issues.clear();
+            let name = "builtin types";
+            let src = "CREATE TABLE a (id serial PRIMARY KEY, b bigserial, u uuid NOT NULL,
+                i int4, r real, j jsonb);";
+            let schema = parse_schemas(src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            for (column, t, auto_increment) in [
+                ("id", "i32!", true),
+                ("b", "i64!", true),
+                ("u", "str!", false),
+                ("i", "i32", false),
+                ("r", "f32", false),
+                ("j", "str", false),
+            ] {
+                let c = schema.schemas.get("a").and_then(|t| t.get_column(column));
+                if c.map(|c| (&c.type_, c.auto_increment))
+                    != Some((&str_to_type(t), auto_increment))
+                {
+                    println!("{}: Wrong type of {}", name, column);
+                    errors += 1;
+                }
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q10";
+            let src = "SELECT ADD_PATH(id, $1) AS p FROM t1";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "str", &mut errors);
+                check_columns(name, &columns, "p:str", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+
+            for (name, src, message, at) in [
+                (
+                    "q11",
+                    "SELECT add_path($1) FROM t1",
+                    "Expected 2 arguments got 1",
+                    "add_path",
+                ),
+                (
+                    "q12",
+                    "SELECT add_path(path, 'x') FROM t1",
+                    "Got type string expected i64",
+                    "path",
+                ),
+                (
+                    "q12.1",
+                    "SELECT no_such_function(path) FROM t1",
+                    "Unknown function",
+                    "no_such_function",
+                ),
+            ] {
+                issues.clear();
+                type_statement(&schema, src, &mut issues, &options);
+                check_issue(name, src, &issues, Level::Error, message, at, &mut errors);
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q13";
+            let src = "SELECT id + 1 AS a, COUNT(*) AS c FROM t1 WHERE old_id > $1 GROUP BY id";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "i64", &mut errors);
+                check_columns(name, &columns, "a:i64!,c:i64!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q14";
+            let src =
+                "SELECT CAST(time AS date) - CAST(time AS date) AS d, CAST(time AS date) + 1 AS e
+                FROM t1 WHERE time > '2024-01-01T10:00:00+02:00'";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "", &mut errors);
+                check_columns(name, &columns, "d:i32!,e:date!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q15";
+            let src = "SELECT path FROM t1 WHERE id = $1 OR old_id = $1 + 1";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, .. } = q {
+                check_arguments(name, &arguments, "i64", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+
+            issues.clear();
+            let name = "q16";
+            let src = "INSERT INTO t1 (path, v) VALUES ($1, 'V1')
+                ON CONFLICT (path) DO UPDATE SET path = $1";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Insert { arguments, .. } = q {
+                check_arguments(name, &arguments, "str!", &mut errors);
+            } else {
+                println!("{} should be insert", name);
+                errors += 1;
+            }
+
+            issues.clear();
+            let name = "q17";
+            let src = "SELECT path FROM t1 WHERE id = $1 AND path = $1 AND $1 IS NOT NULL";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Conflicting types for argument 1",
+                "$1",
+                &mut errors,
+            );
+            if issues.iter().map(|i| i.fragments.len()).ne([1]) {
+                println!("{} should have one fragment", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q18";
+            let src = "SELECT other.u1.name, public.t1.path FROM other.u1
+                JOIN t1 ON t1.id = u1.id WHERE other.u1.id = $1";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "i32", &mut errors);
+                check_columns(name, &columns, "name:str!,path:str!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+
+            issues.clear();
+            let name = "q19";
+            let src = "SELECT other.t1.id FROM t1";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "Unknown table in other",
+                "t1",
+                &mut errors,
+            );
+        }
+
+        {
+            issues.clear();
+            let name = "q20";
+            let src = "SELECT n + n AS a, m / n AS b FROM t3 WHERE n = m";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { arguments, columns } = q {
+                check_arguments(name, &arguments, "", &mut errors);
+                check_columns(name, &columns, "a:dec6.5!,b:dec29.16!", &mut errors);
+            } else {
+                println!("{} should be select", name);
+                errors += 1;
+            }
+        }
+
+        {
+            issues.clear();
+            let name = "q21";
+            let src = "INSERT INTO t4 (name) VALUES ($1)";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Insert { arguments, .. } = q {
+                check_arguments(name, &arguments, "str!", &mut errors);
+            } else {
+                println!("{} should be insert", name);
+                errors += 1;
+            }
+
+            issues.clear();
+            let name = "q22";
+            let src = "INSERT INTO t1 (path, v) VALUES ($1, 'V1')";
+            type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+
+            issues.clear();
+            let name = "q23";
+            let src = "INSERT INTO t4 (id) VALUES ($1)";
+            type_statement(&schema, src, &mut issues, &options);
+            check_issue(
+                name,
+                src,
+                &issues,
+                Level::Error,
+                "No value given for NOT NULL column without default: name",
+                "t4",
+                &mut errors,
+            );
+        }
+
+        {
+            issues.clear();
+            let name = "q24";
+            let src = "SELECT SUM(t3.m) AS a, SUM(t4.id) AS b, SUM(t1.id) AS c
+                FROM t3, t4, t1";
+            let q = type_statement(&schema, src, &mut issues, &options);
+            check_no_errors(name, src, &issues, &mut errors);
+            if let StatementType::Select { columns, .. } = q {
+                check_columns(name, &columns, "a:dec,b:i64,c:dec", &mut errors);
             } else {
                 println!("{} should be select", name);
                 errors += 1;
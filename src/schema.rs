@@ -84,10 +84,15 @@
 
 use crate::{
     type_::{BaseType, FullType},
+    type_expression::{type_expression, ExpressionFlags},
+    type_select::type_select,
+    typer::{ReferenceType, Typer},
     RefOrVal, Type, TypeOptions,
 };
-use alloc::{collections::BTreeMap, vec::Vec};
-use sql_parse::{parse_statements, DataType, Issue, Span, Spanned};
+use alloc::{collections::BTreeMap, format, vec::Vec};
+use sql_parse::{
+    parse_statements, DataType, Expression, Identifier, Issue, OptSpanned, Span, Spanned, Statement,
+};
 
 /// A column in a schema
 #[derive(Debug)]
@@ -99,6 +104,75 @@ pub struct Column<'a> {
     pub type_: FullType<'a>,
     /// True if the column is auto_increment
     pub auto_increment: bool,
+    /// True if the column has a default value
+    pub default: bool,
+    /// True if the column is generated, either computed or as identity, and cannot be written
+    pub generated: bool,
+}
+
+/// The kind of an index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexKind {
+    /// The primary key of the table
+    Primary,
+    /// A unique key or constraint
+    Unique,
+    /// A plain secondary index
+    Index,
+    /// A full text index
+    FullText,
+    /// A spatial index
+    Spatial,
+}
+
+/// A key or index on a table
+#[derive(Debug)]
+pub struct Index<'a> {
+    /// Name of the index if one is given
+    pub name: Option<&'a str>,
+    /// Span of the index definition
+    pub span: Span,
+    /// The kind of index
+    pub kind: IndexKind,
+    /// The indexed columns in order
+    pub columns: Vec<&'a str>,
+    /// Condition of a partial index
+    pub where_: Option<Expression<'a>>,
+}
+
+/// Action taken on referencing rows when the referenced row is updated or deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ForeignKeyAction {
+    /// Reject the change
+    Restrict,
+    /// Update or delete the referencing rows as well
+    Cascade,
+    /// Set the referencing columns to null
+    SetNull,
+    /// Reject the change, the default
+    #[default]
+    NoAction,
+    /// Set the referencing columns to their default values
+    SetDefault,
+}
+
+/// A foreign key from columns of a table to columns of another table
+#[derive(Debug)]
+pub struct ForeignKey<'a> {
+    /// Name of the constraint if one is given
+    pub name: Option<&'a str>,
+    /// Span of the foreign key definition
+    pub span: Span,
+    /// The referencing columns in order
+    pub columns: Vec<&'a str>,
+    /// The referenced table
+    pub references_table: &'a str,
+    /// The referenced columns in order
+    pub references_columns: Vec<&'a str>,
+    /// Action on delete of the referenced row
+    pub on_delete: ForeignKeyAction,
+    /// Action on update of the referenced row
+    pub on_update: ForeignKeyAction,
 }
 
 /// Schema representing a table or view
@@ -110,6 +184,10 @@ pub struct Schema<'a> {
     pub columns: Vec<Column<'a>>,
     /// True if this is a view instead of a table
     pub view: bool,
+    /// Keys and indexes on the table
+    pub indexes: Vec<Index<'a>>,
+    /// Foreign keys from the table
+    pub foreign_keys: Vec<ForeignKey<'a>>,
 }
 
 impl<'a> Schema<'a> {
@@ -123,6 +201,91 @@ impl<'a> Schema<'a> {
             .iter_mut()
             .find(|column| column.identifier == identifier)
     }
+
+    /// Is there a foreign key from column of this table to column of table
+    pub fn references(&self, column: &str, table: &str, table_column: &str) -> bool {
+        self.foreign_keys.iter().any(|fk| {
+            fk.references_table == table
+                && fk
+                    .columns
+                    .iter()
+                    .zip(&fk.references_columns)
+                    .any(|(c, rc)| *c == column && *rc == table_column)
+        })
+    }
+
+    /// The primary key of the table if it has one
+    pub fn primary_key(&self) -> Option<&Index<'a>> {
+        self.indexes.iter().find(|i| i.kind == IndexKind::Primary)
+    }
+
+    /// Can no two rows have the same values in the given columns
+    pub fn is_unique(&self, columns: &[&str]) -> bool {
+        self.indexes.iter().any(|i| {
+            matches!(i.kind, IndexKind::Primary | IndexKind::Unique)
+                && i.where_.is_none()
+                && i.columns.iter().all(|c| columns.contains(c))
+        })
+    }
+
+    /// Add an index checking that its columns exist
+    fn add_index(&mut self, index: Index<'a>, col_spans: &[Span], issues: &mut Vec<Issue>) {
+        for (col, span) in index.columns.iter().zip(col_spans) {
+            if self.get_column(col).is_none() {
+                issues.push(
+                    Issue::err("No such column in table", span)
+                        .frag("Table defined here", &self.identifier_span),
+                );
+            }
+        }
+        if index.kind == IndexKind::Primary {
+            if let Some(pk) = self.primary_key() {
+                issues.push(
+                    Issue::err("Multiple primary keys defined", &index.span)
+                        .frag("Primary key defined here", &pk.span),
+                );
+                return;
+            }
+            // Primary key columns can never be null
+            for col in &index.columns {
+                if let Some(c) = self.get_column_mut(col) {
+                    c.type_.not_null = true;
+                }
+            }
+        }
+        self.indexes.push(index);
+    }
+}
+
+/// Indexes defined inline in a column definition
+fn column_indexes<'a>(identifier: &Identifier<'a>, data_type: &DataType<'a>) -> Vec<Index<'a>> {
+    let mut ans = Vec::new();
+    for p in &data_type.properties {
+        let kind = match p {
+            sql_parse::DataTypeProperty::PrimaryKey(_) => IndexKind::Primary,
+            sql_parse::DataTypeProperty::Unique(_) | sql_parse::DataTypeProperty::UniqueKey(_) => {
+                IndexKind::Unique
+            }
+            _ => continue,
+        };
+        ans.push(Index {
+            name: None,
+            span: p.span(),
+            kind,
+            columns: alloc::vec![identifier.value],
+            where_: None,
+        });
+    }
+    ans
+}
+
+/// A user defined type
+#[derive(Debug)]
+pub struct TypeDef<'a> {
+    /// Span of identifier
+    pub identifier_span: Span,
+    /// The type values of the user defined type have
+    pub type_: Type<'a>,
 }
 
 /// A procedure
@@ -131,7 +294,14 @@ pub struct Procedure {}
 
 /// A function
 #[derive(Debug)]
-pub struct Functions {}
+pub struct Functions<'a> {
+    /// Span of identifier
+    pub identifier_span: Span,
+    /// Names and types of the parameters in order
+    pub params: Vec<(&'a str, FullType<'a>)>,
+    /// Type of the return value
+    pub return_type: FullType<'a>,
+}
 
 /// A description of tables, view, procedures and function in a schemas definition file
 #[derive(Debug)]
@@ -139,10 +309,19 @@ pub struct Functions {}
 pub struct Schemas<'a> {
     /// Map from name to Tables or views
     pub schemas: BTreeMap<&'a str, Schema<'a>>,
+    /// Map from namespace (database or schema) name to the tables or views in it,
+    /// used for qualified names outside of the default namespace. Filled by
+    /// `SET search_path` in postgresql schemas. Mariadb schemas cannot define
+    /// tables in other databases, so to type queries across databases insert the
+    /// tables parsed from the definitions of each other database, as in
+    /// `schemas.namespaces.insert("db2", parse_schemas(db2_src, &mut issues, &options).schemas)`
+    pub namespaces: BTreeMap<&'a str, BTreeMap<&'a str, Schema<'a>>>,
     /// Map from name to procedure
     pub procedures: BTreeMap<&'a str, Procedure>,
     /// Map from name to function
-    pub functions: BTreeMap<&'a str, Functions>,
+    pub functions: BTreeMap<&'a str, Functions<'a>>,
+    /// Map from name to user defined type
+    pub types: BTreeMap<&'a str, TypeDef<'a>>,
 }
 
 pub(crate) fn parse_column<'a>(
@@ -154,15 +333,22 @@ pub(crate) fn parse_column<'a>(
     let mut not_null = false;
     let mut unsigned = false;
     let mut auto_increment = false;
+    let mut default = false;
+    let mut generated = false;
     for p in data_type.properties {
         match p {
             sql_parse::DataTypeProperty::Signed(_) => unsigned = false,
             sql_parse::DataTypeProperty::Unsigned(_) => unsigned = true,
             sql_parse::DataTypeProperty::Null(_) => not_null = false,
             sql_parse::DataTypeProperty::NotNull(_) => not_null = true,
-
             sql_parse::DataTypeProperty::AutoIncrement(_) => auto_increment = true,
-            _ => {} // TODO default,
+            sql_parse::DataTypeProperty::Default(_) => default = true,
+            sql_parse::DataTypeProperty::GeneratedAlways(_)
+            | sql_parse::DataTypeProperty::As(_)
+            | sql_parse::DataTypeProperty::Virtual(_)
+            | sql_parse::DataTypeProperty::Stored(_)
+            | sql_parse::DataTypeProperty::Persistent(_) => generated = true,
+            _ => {}
         }
     }
     let type_ = match data_type.type_ {
@@ -223,7 +409,7 @@ pub(crate) fn parse_column<'a>(
         sql_parse::Type::Boolean => BaseType::Bool.into(),
         sql_parse::Type::Integer(_) => BaseType::Integer.into(),
         sql_parse::Type::Float8 => BaseType::Float.into(),
-        sql_parse::Type::Numeric(_, _, _) => todo!(),
+        sql_parse::Type::Numeric(precision, scale, _) => Type::Decimal { precision, scale },
         sql_parse::Type::Timestamptz => BaseType::TimeStamp.into(),
         sql_parse::Type::Json => BaseType::String.into(),
         sql_parse::Type::Bit(_, _) => BaseType::Bytes.into(),
@@ -239,6 +425,130 @@ pub(crate) fn parse_column<'a>(
             list_hack: false,
         },
         auto_increment,
+        default,
+        generated,
+    }
+}
+
+/// The type of a builtin postgresql type that is parsed as a named type,
+/// and whether it is one of the auto incrementing serial types
+fn builtin_named_type<'a>(name: &str) -> Option<(Type<'a>, bool)> {
+    let t = match name.to_ascii_lowercase().as_str() {
+        "smallserial" | "serial2" => return Some((Type::I16, true)),
+        "serial" | "serial4" => return Some((Type::I32, true)),
+        "bigserial" | "serial8" => return Some((Type::I64, true)),
+        "int2" => Type::I16,
+        "int4" => Type::I32,
+        "int8" => Type::I64,
+        "real" | "float4" => Type::F32,
+        "bool" => BaseType::Bool.into(),
+        "timetz" => BaseType::Time.into(),
+        "uuid" | "jsonb" | "xml" | "inet" | "cidr" | "macaddr" | "interval" | "money"
+        | "citext" | "tsvector" => BaseType::String.into(),
+        _ => return None,
+    };
+    Some((t, false))
+}
+
+/// Parse a column definition, looking up named types among the user defined types
+/// and the builtin types. Other named types are treated as strings
+fn parse_schema_column<'a>(
+    data_type: DataType<'a>,
+    identifier: &'a str,
+    identifier_span: Span,
+    src: &'a str,
+    types: &BTreeMap<&'a str, TypeDef<'a>>,
+    issues: &mut Vec<Issue>,
+) -> Column<'a> {
+    let name = match &data_type.type_ {
+        sql_parse::Type::Named(span) => src.get(span.clone()),
+        _ => None,
+    };
+    let mut column = parse_column(data_type, identifier, identifier_span, issues);
+    if let Some(name) = name {
+        if let Some(named) = types.get(name) {
+            column.type_.t = named.type_.clone();
+        } else if let Some((t, serial)) = builtin_named_type(name) {
+            column.type_.t = t;
+            if serial {
+                // Serial columns are implicitly NOT NULL with a sequence default
+                column.type_.not_null = true;
+                column.auto_increment = true;
+            }
+        }
+    }
+    column
+}
+
+/// Make the tables and views of namespace, None for the default namespace, the ones
+/// that schemas.schemas holds, stashing the current ones away
+fn switch_namespace<'a>(
+    schemas: &mut Schemas<'a>,
+    default: &mut BTreeMap<&'a str, Schema<'a>>,
+    current: &mut Option<&'a str>,
+    namespace: Option<&'a str>,
+) {
+    if *current == namespace {
+        return;
+    }
+    let tables = core::mem::take(&mut schemas.schemas);
+    match *current {
+        Some(c) => {
+            schemas.namespaces.insert(c, tables);
+        }
+        None => *default = tables,
+    }
+    schemas.schemas = match namespace {
+        Some(n) => schemas.namespaces.remove(n).unwrap_or_default(),
+        None => core::mem::take(default),
+    };
+    *current = namespace;
+}
+
+/// Does the DO block with the given statements ignore duplicate_object errors.
+/// The parser does not keep the EXCEPTION clause, so look for it in the source
+fn ignores_duplicate_object(src: &str, block: &[Statement<'_>]) -> bool {
+    let rest = match block.opt_span().and_then(|s| src.get(s.end..)) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let rest = rest[..rest.find("$$").unwrap_or(rest.len())].to_ascii_lowercase();
+    rest.contains("exception") && rest.contains("duplicate_object")
+}
+
+/// Append statements to out, replacing DO blocks by the definitions within them.
+/// Each statement is paired with whether it is in a DO block ignoring
+/// duplicate_object errors
+fn flatten_do<'a>(
+    src: &str,
+    statements: Vec<Statement<'a>>,
+    guarded: bool,
+    out: &mut Vec<(Statement<'a>, bool)>,
+    issues: &mut Vec<Issue>,
+) {
+    for statement in statements {
+        match statement {
+            Statement::Do(block) => {
+                let guarded = guarded || ignores_duplicate_object(src, &block);
+                let mut definitions = Vec::new();
+                for s in block {
+                    if matches!(
+                        s,
+                        Statement::Do(_)
+                            | Statement::CreateTypeEnum(_)
+                            | Statement::CreateTable(_)
+                            | Statement::CreateIndex(_)
+                            | Statement::AlterTable(_)
+                    ) {
+                        definitions.push(s);
+                    } else {
+                        issues.push(Issue::warn("Statement in DO block is ignored", &s));
+                    }
+                }
+                flatten_do(src, definitions, guarded, out, issues);
+            }
+            s => out.push((s, guarded)),
+        }
     }
 }
 
@@ -257,21 +567,32 @@ pub(crate) fn parse_column<'a>(
 /// - Create function
 /// - Create view
 /// - Create procedure
+/// - Create type as enum
 /// - Alter table
+/// - Do blocks containing the above
+/// - Set search_path, placing the following tables and views in that schema
 pub fn parse_schemas<'a>(
     src: &'a str,
     issues: &mut Vec<Issue>,
     options: &TypeOptions,
 ) -> Schemas<'a> {
-    let statements = parse_statements(src, issues, &options.parse_options);
+    let mut statements = Vec::new();
+    let parsed = parse_statements(src, issues, &options.parse_options);
+    flatten_do(src, parsed, false, &mut statements, issues);
 
     let mut schemas = Schemas {
         schemas: Default::default(),
+        namespaces: Default::default(),
         procedures: Default::default(),
         functions: Default::default(),
+        types: Default::default(),
     };
 
-    for statement in statements {
+    // Tables and views are created in the namespace given by the search path
+    let mut namespace = None;
+    let mut default_tables = BTreeMap::new();
+
+    for (statement, ignore_duplicate_object) in statements {
         match statement {
             sql_parse::Statement::CreateTable(t) => {
                 let mut replace = false;
@@ -280,6 +601,8 @@ pub fn parse_schemas<'a>(
                     view: false,
                     identifier_span: t.identifier.span.clone(),
                     columns: Default::default(),
+                    indexes: Default::default(),
+                    foreign_keys: Default::default(),
                 };
 
                 for o in t.create_options {
@@ -306,10 +629,13 @@ pub fn parse_schemas<'a>(
                             identifier,
                             data_type,
                         } => {
-                            let column = parse_column(
+                            let indexes = column_indexes(&identifier, &data_type);
+                            let column = parse_schema_column(
                                 data_type,
                                 identifier.value,
                                 identifier.span.clone(),
+                                src,
+                                &schemas.types,
                                 issues,
                             );
                             if let Some(oc) = schema.get_column(column.identifier) {
@@ -319,6 +645,9 @@ pub fn parse_schemas<'a>(
                                 );
                             } else {
                                 schema.columns.push(column);
+                                for index in indexes {
+                                    schema.add_index(index, &[identifier.span()], issues);
+                                }
                             }
                         }
                         sql_parse::CreateDefinition::ConstraintDefinition { .. } => {}
@@ -342,10 +671,12 @@ pub fn parse_schemas<'a>(
             }
             sql_parse::Statement::CreateView(v) => {
                 let mut replace = false;
-                let schema = Schema {
+                let mut schema = Schema {
                     view: true,
                     identifier_span: v.name.span.clone(),
                     columns: Default::default(),
+                    indexes: Default::default(),
+                    foreign_keys: Default::default(),
                 };
                 for o in v.create_options {
                     match o {
@@ -364,7 +695,29 @@ pub fn parse_schemas<'a>(
                         sql_parse::CreateOption::SqlSecurityUser(_, _) => {}
                     }
                 }
-                // TODO typecheck view query to find schema
+                {
+                    let mut typer = Typer {
+                        schemas: &schemas,
+                        issues,
+                        reference_types: Vec::new(),
+                        arg_uses: Default::default(),
+                        options,
+                    };
+                    let t = type_select(&mut typer, &v.select, true);
+                    for column in t.into_result_columns() {
+                        // Columns without a name cannot be referenced through the view
+                        if let Some(name) = column.name {
+                            schema.columns.push(Column {
+                                identifier: name,
+                                identifier_span: column.span,
+                                type_: column.type_,
+                                auto_increment: false,
+                                default: false,
+                                generated: false,
+                            });
+                        }
+                    }
+                }
                 match schemas.schemas.entry(v.name.value) {
                     alloc::collections::btree_map::Entry::Occupied(mut e) => {
                         if replace {
@@ -382,7 +735,53 @@ pub fn parse_schemas<'a>(
                 }
             }
             sql_parse::Statement::CreateTrigger(_) => {}
-            // sql_parse::Statement::CreateFunction(_) => todo!(),
+            sql_parse::Statement::CreateFunction(f) => {
+                let replace = f
+                    .create_options
+                    .iter()
+                    .any(|o| matches!(o, sql_parse::CreateOption::OrReplace(_)));
+                let mut params = Vec::new();
+                for (_, identifier, data_type) in f.params {
+                    let column = parse_schema_column(
+                        data_type,
+                        identifier.value,
+                        identifier.span(),
+                        src,
+                        &schemas.types,
+                        issues,
+                    );
+                    params.push((identifier.value, column.type_));
+                }
+                let return_type = parse_schema_column(
+                    f.return_type,
+                    "",
+                    f.returns_span,
+                    src,
+                    &schemas.types,
+                    issues,
+                )
+                .type_;
+                let function = Functions {
+                    identifier_span: f.name.span(),
+                    params,
+                    return_type,
+                };
+                match schemas.functions.entry(f.name.value) {
+                    alloc::collections::btree_map::Entry::Occupied(mut e) => {
+                        if replace {
+                            e.insert(function);
+                        } else if f.if_not_exists.is_none() {
+                            issues.push(
+                                Issue::err("Function already defined", &f.name)
+                                    .frag("Defined here", &e.get().identifier_span),
+                            );
+                        }
+                    }
+                    alloc::collections::btree_map::Entry::Vacant(e) => {
+                        e.insert(function);
+                    }
+                }
+            }
             // sql_parse::Statement::Select(_) => todo!(),
             // sql_parse::Statement::Delete(_) => todo!(),
             // sql_parse::Statement::Insert(_) => todo!(),
@@ -469,7 +868,34 @@ pub fn parse_schemas<'a>(
                     }
                 }
             }
-            sql_parse::Statement::Set(_) => {}
+            sql_parse::Statement::Set(s) => {
+                for (key, value) in &s.values {
+                    if !options.parse_options.get_dialect().is_postgresql()
+                        || !key.value.eq_ignore_ascii_case("search_path")
+                    {
+                        continue;
+                    }
+                    match value {
+                        Expression::Identifier(parts) => match parts.as_slice() {
+                            [sql_parse::IdentifierPart::Name(n)] => {
+                                let n = if options.is_default_namespace(n.value) {
+                                    None
+                                } else {
+                                    Some(n.value)
+                                };
+                                switch_namespace(
+                                    &mut schemas,
+                                    &mut default_tables,
+                                    &mut namespace,
+                                    n,
+                                );
+                            }
+                            _ => issues.push(Issue::err("Invalid search path", value)),
+                        },
+                        _ => issues.push(Issue::err("Invalid search path", value)),
+                    }
+                }
+            }
             sql_parse::Statement::AlterTable(a) => {
                 let e = match schemas.schemas.entry(a.table.value) {
                     alloc::collections::btree_map::Entry::Occupied(e) => {
@@ -487,10 +913,111 @@ pub fn parse_schemas<'a>(
                         continue;
                     }
                 };
+                // Foreign keys are validated after the other changes, since
+                // the referenced table may be the table itself
+                let mut foreign_keys = Vec::new();
                 for s in a.alter_specifications {
                     match s {
-                        sql_parse::AlterSpecification::AddIndex { .. } => {}
-                        sql_parse::AlterSpecification::AddForeignKey { .. } => {}
+                        sql_parse::AlterSpecification::AddIndex {
+                            add_span,
+                            index_type,
+                            name,
+                            cols,
+                            ..
+                        } => {
+                            let kind = match index_type {
+                                sql_parse::IndexType::Index(_) => IndexKind::Index,
+                                sql_parse::IndexType::Primary(_) => IndexKind::Primary,
+                                sql_parse::IndexType::Unique(_) => IndexKind::Unique,
+                                sql_parse::IndexType::FullText(_) => IndexKind::FullText,
+                                sql_parse::IndexType::Spatial(_) => IndexKind::Spatial,
+                            };
+                            let col_spans: Vec<_> = cols.iter().map(|c| c.name.span()).collect();
+                            let span = add_span.join_span(&index_type).join_span(&col_spans);
+                            e.add_index(
+                                Index {
+                                    name: name.map(|n| n.value),
+                                    span,
+                                    kind,
+                                    columns: cols.iter().map(|c| c.name.value).collect(),
+                                    where_: None,
+                                },
+                                &col_spans,
+                                issues,
+                            );
+                        }
+                        sql_parse::AlterSpecification::AddForeignKey {
+                            add_span,
+                            constraint,
+                            name,
+                            cols,
+                            references_table,
+                            references_cols,
+                            ons,
+                            ..
+                        } => {
+                            let mut fk = ForeignKey {
+                                name: name
+                                    .or_else(|| constraint.and_then(|(_, n)| n))
+                                    .map(|n| n.value),
+                                span: add_span
+                                    .join_span(&references_table)
+                                    .join_span(&references_cols)
+                                    .join_span(&ons),
+                                columns: Vec::new(),
+                                references_table: references_table.value,
+                                references_columns: references_cols
+                                    .iter()
+                                    .map(|c| c.value)
+                                    .collect(),
+                                on_delete: Default::default(),
+                                on_update: Default::default(),
+                            };
+                            for on in ons {
+                                let action = match on.action {
+                                    sql_parse::ForeignKeyOnAction::Restrict(_) => {
+                                        ForeignKeyAction::Restrict
+                                    }
+                                    sql_parse::ForeignKeyOnAction::Cascade(_) => {
+                                        ForeignKeyAction::Cascade
+                                    }
+                                    sql_parse::ForeignKeyOnAction::SetNull(_) => {
+                                        ForeignKeyAction::SetNull
+                                    }
+                                    sql_parse::ForeignKeyOnAction::NoAction(_) => {
+                                        ForeignKeyAction::NoAction
+                                    }
+                                    sql_parse::ForeignKeyOnAction::SetDefault(_) => {
+                                        ForeignKeyAction::SetDefault
+                                    }
+                                };
+                                match on.type_ {
+                                    sql_parse::ForeignKeyOnType::Update(_) => fk.on_update = action,
+                                    sql_parse::ForeignKeyOnType::Delete(_) => fk.on_delete = action,
+                                }
+                            }
+                            let mut col_types = Vec::new();
+                            for col in &cols {
+                                fk.columns.push(col.name.value);
+                                match e.get_column(col.name.value) {
+                                    Some(c) => col_types.push(Some(c.type_.clone())),
+                                    None => {
+                                        issues.push(
+                                            Issue::err("No such column in table", &col.name)
+                                                .frag("Table defined here", &e.identifier_span),
+                                        );
+                                        col_types.push(None);
+                                    }
+                                }
+                            }
+                            foreign_keys.push((
+                                fk,
+                                cols.into_iter().map(|c| c.name).collect::<Vec<_>>(),
+                                col_types,
+                                references_table,
+                                references_cols,
+                            ));
+                        }
                         sql_parse::AlterSpecification::Modify {
                             if_exists,
                             col,
@@ -509,26 +1036,117 @@ pub fn parse_schemas<'a>(
                                     continue;
                                 }
                             };
-                            *c = parse_column(definition, c.identifier, col.span(), issues);
+                            *c = parse_schema_column(
+                                definition,
+                                c.identifier,
+                                col.span(),
+                                src,
+                                &schemas.types,
+                                issues,
+                            );
                         }
                         sql_parse::AlterSpecification::AddColumn {
                             identifier,
                             data_type,
+                            if_not_exists_span,
                             ..
                         } => {
-                            e.columns.push(parse_column(
+                            if let Some(oc) = e.get_column(identifier.value) {
+                                if if_not_exists_span.is_none() {
+                                    issues.push(
+                                        Issue::err("Column already defined", &identifier)
+                                            .frag("Defined here", &oc.identifier_span),
+                                    );
+                                }
+                                continue;
+                            }
+                            let indexes = column_indexes(&identifier, &data_type);
+                            e.columns.push(parse_schema_column(
                                 data_type,
                                 identifier.as_str(),
                                 identifier.span(),
+                                src,
+                                &schemas.types,
                                 issues,
                             ));
+                            for index in indexes {
+                                e.add_index(index, &[identifier.span()], issues);
+                            }
                         }
                         sql_parse::AlterSpecification::OwnerTo { .. } => {}
                     }
                 }
+                for (fk, cols, col_types, references_table, references_cols) in foreign_keys {
+                    let rt = match schemas.schemas.get(references_table.value) {
+                        Some(rt) => rt,
+                        None => {
+                            issues.push(Issue::err("No such table", &references_table));
+                            continue;
+                        }
+                    };
+                    if cols.len() != references_cols.len() {
+                        let mut issue = Issue::err(
+                            "Foreign key has a different number of columns than referenced",
+                            &fk.span,
+                        );
+                        // The lists may be empty when recovering from parse errors
+                        if let Some(span) = cols.opt_span() {
+                            issue = issue.frag(format!("{} columns", cols.len()), &span);
+                        }
+                        if let Some(span) = references_cols.opt_span() {
+                            issue = issue.frag(
+                                format!("{} referenced columns", references_cols.len()),
+                                &span,
+                            );
+                        }
+                        issues.push(issue);
+                    }
+                    for ((col, t), rcol) in cols.iter().zip(&col_types).zip(&references_cols) {
+                        let rc = match rt.get_column(rcol.value) {
+                            Some(rc) => rc,
+                            None => {
+                                issues.push(
+                                    Issue::err("No such column in table", rcol)
+                                        .frag("Table defined here", &rt.identifier_span),
+                                );
+                                continue;
+                            }
+                        };
+                        if let Some(t) = t {
+                            if t.base() != rc.type_.base() {
+                                issues.push(
+                                    Issue::err("Incompatible types in foreign key", &fk.span)
+                                        .frag(format!("Of type {}", t.t), col)
+                                        .frag(format!("Of type {}", rc.type_.t), rcol),
+                                );
+                            }
+                        }
+                    }
+                    if let Some(e) = schemas.schemas.get_mut(a.table.value) {
+                        e.foreign_keys.push(fk);
+                    }
+                }
             }
-            sql_parse::Statement::Do(_) => {
-                //todo!()
+            sql_parse::Statement::CreateTypeEnum(t) => {
+                let type_ = Type::Enum(RefOrVal::Val(
+                    t.values.into_iter().map(|v| v.value).collect(),
+                ));
+                match schemas.types.entry(t.name.value) {
+                    alloc::collections::btree_map::Entry::Occupied(e) => {
+                        if !ignore_duplicate_object {
+                            issues.push(
+                                Issue::err("Type already defined", &t.name)
+                                    .frag("Defined here", &e.get().identifier_span),
+                            );
+                        }
+                    }
+                    alloc::collections::btree_map::Entry::Vacant(e) => {
+                        e.insert(TypeDef {
+                            identifier_span: t.name.span(),
+                            type_,
+                        });
+                    }
+                }
             }
             // sql_parse::Statement::Block(_) => todo!(),
             // sql_parse::Statement::If(_) => todo!(),
@@ -537,19 +1155,60 @@ pub fn parse_schemas<'a>(
             // sql_parse::Statement::Replace(_) => todo!(),
             // sql_parse::Statement::Case(_) => todo!(),
             sql_parse::Statement::CreateIndex(ci) => {
-                if let Some(table) = schemas.schemas.get(ci.table_name.as_str()) {
-                    for col in &ci.column_names {
-                        if table.get_column(col).is_none() {
-                            issues.push(
-                                Issue::err("No such column in table", col)
-                                    .frag("Table defined here", &table.identifier_span),
-                            );
-                        }
+                let table = match schemas.schemas.get(ci.table_name.as_str()) {
+                    Some(table) => table,
+                    None => {
+                        issues.push(Issue::err("No such table", &ci.table_name));
+                        continue;
                     }
-
-                    // TODO type where_
-                } else {
-                    issues.push(Issue::err("No such table", &ci.table_name));
+                };
+                if let Some((_, where_)) = &ci.where_ {
+                    let mut typer = Typer {
+                        schemas: &schemas,
+                        issues,
+                        reference_types: alloc::vec![ReferenceType {
+                            name: Some(ci.table_name.value),
+                            table: Some(ci.table_name.value),
+                            namespace: None,
+                            span: ci.table_name.span(),
+                            columns: table
+                                .columns
+                                .iter()
+                                .map(|c| (c.identifier, c.type_.clone()))
+                                .collect(),
+                            hidden: Vec::new(),
+                            keys: Vec::new(),
+                        }],
+                        arg_uses: Default::default(),
+                        options,
+                    };
+                    let t = type_expression(
+                        &mut typer,
+                        where_,
+                        ExpressionFlags::default(),
+                        BaseType::Bool,
+                    );
+                    typer.ensure_base(where_, &t, BaseType::Bool);
+                }
+                let unique = ci
+                    .create_options
+                    .iter()
+                    .any(|o| matches!(o, sql_parse::CreateOption::Unique(_)));
+                let col_spans: Vec<_> = ci.column_names.iter().map(|c| c.span()).collect();
+                let span = ci.span();
+                let index = Index {
+                    name: Some(ci.index_name.value),
+                    span,
+                    kind: if unique {
+                        IndexKind::Unique
+                    } else {
+                        IndexKind::Index
+                    },
+                    columns: ci.column_names.iter().map(|c| c.value).collect(),
+                    where_: ci.where_.map(|(_, w)| w),
+                };
+                if let Some(table) = schemas.schemas.get_mut(ci.table_name.as_str()) {
+                    table.add_index(index, &col_spans, issues);
                 }
             }
             sql_parse::Statement::Commit(_) => (),
@@ -557,5 +1216,6 @@ pub fn parse_schemas<'a>(
             s => issues.push(Issue::err("Unsupported statement in schema definition", &s)),
         }
     }
+    switch_namespace(&mut schemas, &mut default_tables, &mut namespace, None);
     schemas
 }
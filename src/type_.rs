@@ -29,6 +29,8 @@ pub enum BaseType {
     Bytes,
     Date,
     DateTime,
+    /// The value must be some kind of fixed point decimal
+    Decimal,
     /// The value must be some kind of float
     Float,
     /// The value must be some kind of integer
@@ -46,6 +48,7 @@ impl Display for BaseType {
             BaseType::Bytes => f.write_str("bytes"),
             BaseType::Date => f.write_str("date"),
             BaseType::DateTime => f.write_str("datetime"),
+            BaseType::Decimal => f.write_str("decimal"),
             BaseType::Float => f.write_str("float"),
             BaseType::Integer => f.write_str("integer"),
             BaseType::String => f.write_str("string"),
@@ -68,6 +71,10 @@ pub enum Type<'a> {
     #[doc(hidden)]
     Args(BaseType, Vec<(usize, ArgType, Span)>),
     Base(BaseType),
+    Decimal {
+        precision: usize,
+        scale: usize,
+    },
     Enum(RefOrVal<'a, Vec<Cow<'a, str>>>),
     F32,
     F64,
@@ -98,6 +105,7 @@ impl<'a> Display for Type<'a> {
                 f.write_char(')')
             }
             Type::Base(t) => t.fmt(f),
+            Type::Decimal { precision, scale } => write!(f, "decimal({}, {})", precision, scale),
             Type::F32 => f.write_str("f32"),
             Type::F64 => f.write_str("f64"),
             Type::I16 => f.write_str("i16"),
@@ -136,20 +144,12 @@ impl<'a> Display for Type<'a> {
 }
 
 impl<'a> Type<'a> {
-    /// Make a none owning clone of the type
-    pub(crate) fn ref_clone(&'a self) -> Self {
-        match self {
-            Type::Enum(e) => Type::Enum(e.ref_clone()),
-            Type::Set(e) => Type::Set(e.ref_clone()),
-            t => t.clone(),
-        }
-    }
-
     /// Compute the canonical base type
     pub fn base(&self) -> BaseType {
         match self {
             Type::Args(t, _) => *t,
             Type::Base(t) => *t,
+            Type::Decimal { .. } => BaseType::Decimal,
             Type::Enum(_) => BaseType::String,
             Type::F32 => BaseType::Float,
             Type::F64 => BaseType::Float,
@@ -184,14 +184,6 @@ pub struct FullType<'a> {
 }
 
 impl<'a> FullType<'a> {
-    pub(crate) fn ref_clone(&'a self) -> Self {
-        FullType {
-            t: self.t.ref_clone(),
-            not_null: self.not_null,
-            list_hack: self.list_hack,
-        }
-    }
-
     pub(crate) fn new(t: impl Into<Type<'a>>, not_null: bool) -> Self {
         Self {
             t: t.into(),
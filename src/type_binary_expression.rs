@@ -11,15 +11,110 @@
 // limitations under the License.
 
 use alloc::format;
-use sql_parse::{BinaryOperator, Expression, Issue, Span};
+use sql_parse::{BinaryOperator, Expression, Issue, SQLDialect, Span};
 
 use crate::{
     type_::{BaseType, FullType},
     type_expression::{type_expression, ExpressionFlags},
-    typer::Typer,
+    typer::{decimal_digits, decimal_type, int_width, Typer},
     Type,
 };
 
+/// The type of dividing decimals or integers of type lhs and rhs
+fn decimal_division<'a>(dialect: SQLDialect, lhs: &Type<'a>, rhs: &Type<'a>) -> Type<'a> {
+    let (p1, s1) = match decimal_digits(lhs) {
+        Some(v) => v,
+        None => return BaseType::Decimal.into(),
+    };
+    let s2 = decimal_digits(rhs).map_or(0, |(_, s)| s);
+    // MariaDB adds div_precision_increment (4) digits to the scale of the dividend,
+    // PostgreSQL gives at least 16 digits of scale
+    let scale = if dialect.is_postgresql() {
+        s1.max(s2).max(16)
+    } else {
+        s1 + 4
+    };
+    decimal_type(dialect, p1.saturating_sub(s1) + s2 + scale, scale)
+}
+
+/// The type of an arithmetic operation on values of type lhs and rhs, that are both
+/// convertible to t
+fn arithmetic_type<'a>(
+    typer: &Typer<'a, '_>,
+    op: &BinaryOperator,
+    lhs: &Type<'a>,
+    rhs: &Type<'a>,
+    t: Type<'a>,
+) -> Type<'a> {
+    let dialect = typer.dialect();
+    let maria = dialect.is_maria();
+    match t.base() {
+        BaseType::Integer if maria => {
+            // MariaDB computes integer arithmetic in 64 bits, unsigned if either operand is
+            if matches!(op, BinaryOperator::Divide) {
+                decimal_division(dialect, lhs, rhs)
+            } else if int_width(lhs).is_some_and(|(s, _)| !s)
+                || int_width(rhs).is_some_and(|(s, _)| !s)
+            {
+                Type::U64
+            } else if int_width(&t).is_some() {
+                Type::I64
+            } else {
+                t
+            }
+        }
+        BaseType::Float if maria && t == Type::F32 => Type::F64,
+        BaseType::Decimal => match (op, decimal_digits(lhs), decimal_digits(rhs)) {
+            (BinaryOperator::Mult, Some((p1, s1)), Some((p2, s2))) => {
+                decimal_type(dialect, p1 + p2, s1 + s2)
+            }
+            (BinaryOperator::Divide, _, _) => decimal_division(dialect, lhs, rhs),
+            (BinaryOperator::Add | BinaryOperator::Subtract, _, _) => match t {
+                // One more digit is needed for the carry
+                Type::Decimal { precision, scale } => decimal_type(dialect, precision + 1, scale),
+                t => t,
+            },
+            _ => t,
+        },
+        _ => t,
+    }
+}
+
+/// The type of adding or subtracting days to or from a date, or subtracting two dates.
+/// MariaDB instead converts temporal values to numbers like YYYYMMDDhhmmss, so the
+/// result of adding or subtracting integers or temporal values is an integer
+fn date_arithmetic_type<'a>(
+    typer: &Typer<'a, '_>,
+    op: &BinaryOperator,
+    lhs: &Type<'a>,
+    rhs: &Type<'a>,
+) -> Option<Type<'a>> {
+    if !typer.dialect().is_postgresql() {
+        let temporal = |t: BaseType| {
+            matches!(
+                t,
+                BaseType::Date | BaseType::DateTime | BaseType::TimeStamp | BaseType::Time
+            )
+        };
+        let (l, r) = (lhs.base(), rhs.base());
+        let numeric = |t: BaseType| temporal(t) || t == BaseType::Integer;
+        return match op {
+            BinaryOperator::Add | BinaryOperator::Subtract
+                if (temporal(l) || temporal(r)) && numeric(l) && numeric(r) =>
+            {
+                Some(Type::I64)
+            }
+            _ => None,
+        };
+    }
+    match (lhs.base(), op, rhs.base()) {
+        (BaseType::Date, BinaryOperator::Add | BinaryOperator::Subtract, BaseType::Integer)
+        | (BaseType::Integer, BinaryOperator::Add, BaseType::Date) => Some(BaseType::Date.into()),
+        (BaseType::Date, BinaryOperator::Subtract, BaseType::Date) => Some(Type::I32),
+        _ => None,
+    }
+}
+
 pub(crate) fn type_binary_expression<'a, 'b>(
     typer: &mut Typer<'a, 'b>,
     op: &BinaryOperator,
@@ -79,8 +174,27 @@ pub(crate) fn type_binary_expression<'a, 'b>(
         }
     };
 
-    let lhs_type = type_expression(typer, lhs, flags, context);
-    let rhs_type = type_expression(typer, rhs, flags, context);
+    let comparison = matches!(
+        op,
+        BinaryOperator::Eq
+            | BinaryOperator::Neq
+            | BinaryOperator::GtEq
+            | BinaryOperator::Gt
+            | BinaryOperator::LtEq
+            | BinaryOperator::Lt
+            | BinaryOperator::NullSafeEq
+    );
+    let mut lhs_type = type_expression(typer, lhs, flags, context);
+    let rhs_type = if comparison {
+        // Literals are converted to the type of the other operand
+        let rhs_type = type_expression(typer, rhs, flags, lhs_type.base());
+        if let Expression::String(_) = lhs {
+            lhs_type = type_expression(typer, lhs, flags, rhs_type.base());
+        }
+        rhs_type
+    } else {
+        type_expression(typer, rhs, flags, context)
+    };
     match op {
         BinaryOperator::Or | BinaryOperator::Xor | BinaryOperator::And => {
             typer.ensure_base(lhs, &lhs_type, BaseType::Bool);
@@ -106,6 +220,8 @@ pub(crate) fn type_binary_expression<'a, 'b>(
                         .frag(format!("Of type {}", rhs_type.t), rhs),
                 );
             }
+            typer.ensure_enum_value(rhs, &lhs_type.t);
+            typer.ensure_enum_value(lhs, &rhs_type.t);
             FullType::new(BaseType::Bool, lhs_type.not_null && rhs_type.not_null)
         }
         BinaryOperator::NullSafeEq => {
@@ -116,6 +232,8 @@ pub(crate) fn type_binary_expression<'a, 'b>(
                         .frag(format!("Of type {}", rhs_type.t), rhs),
                 );
             }
+            typer.ensure_enum_value(rhs, &lhs_type.t);
+            typer.ensure_enum_value(lhs, &rhs_type.t);
             FullType::new(BaseType::Bool, true)
         }
         BinaryOperator::ShiftLeft
@@ -133,9 +251,12 @@ pub(crate) fn type_binary_expression<'a, 'b>(
         | BinaryOperator::Div
         | BinaryOperator::Mod
         | BinaryOperator::Mult => {
-            if let Some(t) = typer.matched_type(&lhs_type, &rhs_type) {
+            if let Some(t) = date_arithmetic_type(typer, op, &lhs_type, &rhs_type) {
+                FullType::new(t, lhs_type.not_null && rhs_type.not_null)
+            } else if let Some(t) = typer.matched_type(&lhs_type, &rhs_type) {
                 match t.base() {
-                    BaseType::Any | BaseType::Float | BaseType::Integer => {
+                    BaseType::Any | BaseType::Decimal | BaseType::Float | BaseType::Integer => {
+                        let t = arithmetic_type(typer, op, &lhs_type.t, &rhs_type.t, t);
                         FullType::new(t, lhs_type.not_null && rhs_type.not_null)
                     }
                     _ => {
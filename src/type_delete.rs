@@ -11,7 +11,7 @@
 // limitations under the License.
 
 use alloc::vec::Vec;
-use sql_parse::{issue_todo, Delete, Issue, OptSpanned, Spanned};
+use sql_parse::{Delete, Issue, OptSpanned, Spanned};
 
 use crate::{
     type_expression::{type_expression, ExpressionFlags},
@@ -42,20 +42,7 @@ pub(crate) fn type_delete<'a, 'b>(typer: &mut Typer<'a, 'b>, delete: &Delete<'a>
             type_reference(typer, reference, false);
         }
         for table in &delete.tables {
-            let identifier = match table.as_slice() {
-                [v] => v,
-                _ => {
-                    typer.issues.push(issue_todo!(&delete.tables[0]
-                        .opt_span()
-                        .expect("table span in type_delete")));
-                    return;
-                }
-            };
-            if !typer.schemas.schemas.contains_key(&identifier.value) {
-                typer
-                    .issues
-                    .push(Issue::err("Unknown table or view", identifier))
-            }
+            typer.get_schema(table);
         }
     } else {
         if delete.tables.len() > 1 {
@@ -64,29 +51,25 @@ pub(crate) fn type_delete<'a, 'b>(typer: &mut Typer<'a, 'b>, delete: &Delete<'a>
                 &delete.tables.opt_span().unwrap(),
             ));
         }
-        let identifier = match delete.tables[0].as_slice() {
-            [v] => v,
-            _ => {
-                typer.issues.push(issue_todo!(&delete.tables[0]
-                    .opt_span()
-                    .expect("table span in type_delete")));
-                return;
-            }
-        };
-        if let Some(s) = typer.schemas.schemas.get(&identifier.value) {
+        if let Some(s) = typer.get_schema(&delete.tables[0]) {
+            let identifier = delete.tables[0].last().expect("table name");
+            let namespace = typer.other_namespace(&delete.tables[0]);
             let mut columns = Vec::new();
             for col in &s.columns {
-                columns.push((col.identifier, col.type_.ref_clone()));
+                columns.push((col.identifier, col.type_.clone()));
             }
             typer.reference_types.push(ReferenceType {
                 name: Some(identifier.value),
+                table: match namespace {
+                    None => Some(identifier.value),
+                    Some(_) => None,
+                },
+                namespace,
                 span: identifier.span(),
                 columns,
+                hidden: Vec::new(),
+                keys: Vec::new(),
             });
-        } else {
-            typer
-                .issues
-                .push(Issue::err("Unknown table or view", identifier));
         }
         for reference in &delete.using {
             type_reference(typer, reference, false);
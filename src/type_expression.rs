@@ -10,9 +10,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use alloc::{format, string::ToString, vec};
+use alloc::{format, string::ToString, vec, vec::Vec};
 use core::ops::Deref;
-use sql_parse::{issue_todo, Expression, Issue, Span, UnaryOperator};
+use sql_parse::{issue_todo, Expression, Issue, Span, UnaryOperator, When};
 
 use crate::{
     schema::parse_column,
@@ -62,15 +62,59 @@ fn type_unary_expression<'a, 'b>(
     op_span: &Span,
     operand: &Expression<'a>,
     flags: ExpressionFlags,
+    context: BaseType,
 ) -> FullType<'a> {
     match op {
-        UnaryOperator::Binary
-        | UnaryOperator::Collate
-        | UnaryOperator::LogicalNot
-        | UnaryOperator::Minus => {
-            let _op_type = type_expression(typer, operand, flags.with_true(false), BaseType::Any);
-            typer.issues.push(issue_todo!(op_span));
-            FullType::invalid()
+        UnaryOperator::Binary => {
+            let op_type = type_expression(typer, operand, flags.with_true(false), BaseType::Bytes);
+            // Any value can be converted to a binary string, but unconstrained
+            // arguments should be given as bytes
+            if op_type.base() == BaseType::Any {
+                typer.ensure_base(operand, &op_type, BaseType::Bytes);
+            }
+            FullType::new(BaseType::Bytes, op_type.not_null)
+        }
+        UnaryOperator::Collate => {
+            let op_type = type_expression(typer, operand, flags.with_true(false), BaseType::String);
+            typer.ensure_base(operand, &op_type, BaseType::String);
+            if let Type::Args(..) = op_type.t {
+                FullType::new(BaseType::String, op_type.not_null)
+            } else {
+                op_type
+            }
+        }
+        UnaryOperator::LogicalNot => {
+            let op_type = type_expression(typer, operand, flags.with_true(false), BaseType::Bool);
+            typer.ensure_base(operand, &op_type, BaseType::Bool);
+            FullType::new(BaseType::Bool, op_type.not_null)
+        }
+        UnaryOperator::Minus => {
+            // The operand must be numeric, without further context a double is the most general
+            let context = match context {
+                BaseType::Integer | BaseType::Float | BaseType::Decimal => context,
+                _ => BaseType::Float,
+            };
+            let op_type = type_expression(typer, operand, flags.with_true(false), context);
+            let t = match &op_type.t {
+                // Negating an unsigned value may yield a value that only fits
+                // in a wider signed type
+                Type::U8 => Type::I16,
+                Type::U16 => Type::I32,
+                Type::U32 | Type::U64 => Type::I64,
+                t => match t.base() {
+                    BaseType::Any | BaseType::Decimal | BaseType::Float | BaseType::Integer => {
+                        t.clone()
+                    }
+                    _ => {
+                        typer.issues.push(
+                            Issue::err("Expected numeric type", op_span)
+                                .frag(format!("Of type {}", op_type.t), operand),
+                        );
+                        Type::Invalid
+                    }
+                },
+            };
+            FullType::new(t, op_type.not_null)
         }
         UnaryOperator::Not => {
             let op_type = type_expression(typer, operand, flags.with_true(false), BaseType::Bool);
@@ -80,11 +124,118 @@ fn type_unary_expression<'a, 'b>(
     }
 }
 
+fn type_case_expression<'a, 'b>(
+    typer: &mut Typer<'a, 'b>,
+    case_span: &Span,
+    value: Option<&Expression<'a>>,
+    whens: &[When<'a>],
+    else_: Option<&Expression<'a>>,
+    flags: ExpressionFlags,
+) -> FullType<'a> {
+    let flags = flags.without_values();
+    let value_type = value.map(|v| (v, type_expression(typer, v, flags, BaseType::Any)));
+    let mut ans: Option<(&Expression<'a>, FullType<'a>)> = None;
+    let mut not_null = else_.is_some();
+    let mut branch_types = Vec::new();
+    let branches = whens
+        .iter()
+        .map(|w| (Some(w), &w.then))
+        .chain(else_.map(|e| (None, e)));
+    for (when, then) in branches {
+        if let Some(when) = when {
+            if let Some((v, vt)) = &value_type {
+                let t = type_expression(typer, &when.when, flags, vt.base());
+                if typer.matched_type(vt, &t).is_none() {
+                    typer.issues.push(
+                        Issue::err("Type error in comparison", &when.when_span)
+                            .frag(format!("Of type {}", vt.t), *v)
+                            .frag(format!("Of type {}", t.t), &when.when),
+                    );
+                }
+            } else {
+                let t = type_expression(typer, &when.when, flags, BaseType::Bool);
+                typer.ensure_base(&when.when, &t, BaseType::Bool);
+            }
+        }
+        let context = ans.as_ref().map(|(_, t)| t.base()).unwrap_or(BaseType::Any);
+        let t = type_expression(typer, then, flags, context);
+        not_null = not_null && t.not_null;
+        branch_types.push(t.t.clone());
+        ans = match ans {
+            None => Some((then, t)),
+            Some((e, at)) => {
+                if at.t == t.t {
+                    Some((e, at))
+                } else if let Some(mt) = typer.matched_type(&at, &t) {
+                    Some((e, FullType::new(mt, false)))
+                } else {
+                    typer.issues.push(
+                        Issue::err("Incompatible types in case", case_span)
+                            .frag(format!("Of type {}", at.t), e)
+                            .frag(format!("Of type {}", t.t), then),
+                    );
+                    Some((e, at))
+                }
+            }
+        };
+    }
+    match ans {
+        Some((_, t)) => {
+            // Arguments take the type unified over all branches
+            typer.constrain_branch_args(&branch_types, &t.t);
+            FullType::new(t.t, not_null)
+        }
+        None => FullType::invalid(),
+    }
+}
+
+/// Is s in a format accepted for string literals of the temporal type t
+fn is_temporal_literal(t: BaseType, s: &str) -> bool {
+    let digits = |s: &str, n: core::ops::RangeInclusive<usize>| {
+        n.contains(&s.len()) && s.bytes().all(|c| c.is_ascii_digit())
+    };
+    let date = |s: &str| {
+        let mut p = s.split('-');
+        matches!(
+            (p.next(), p.next(), p.next(), p.next()),
+            (Some(y), Some(m), Some(d), None)
+                if digits(y, 4..=4) && digits(m, 1..=2) && digits(d, 1..=2)
+        )
+    };
+    let time = |s: &str| {
+        let s = match s.split_once('.') {
+            Some((s, frac)) if digits(frac, 1..=6) => s,
+            Some(_) => return false,
+            None => s,
+        };
+        let mut p = s.split(':');
+        matches!(
+            (p.next(), p.next(), p.next(), p.next()),
+            (Some(h), Some(m), sec, None)
+                if digits(h, 1..=3) && digits(m, 2..=2) && sec.into_iter().all(|s| digits(s, 2..=2))
+        )
+    };
+    match t {
+        BaseType::Date => date(s),
+        BaseType::DateTime | BaseType::TimeStamp => match s.split_once([' ', 'T']) {
+            Some((d, tm)) => {
+                // Strip a time zone offset
+                let tm = tm.trim_end_matches('Z');
+                let tm = tm.split(['+', '-']).next().unwrap_or(tm);
+                date(d) && time(tm)
+            }
+            None => date(s),
+        },
+        BaseType::Time => time(s.strip_prefix('-').unwrap_or(s)),
+        _ => false,
+    }
+}
+
 pub(crate) fn type_expression<'a, 'b>(
     typer: &mut Typer<'a, 'b>,
     expression: &Expression<'a>,
     flags: ExpressionFlags,
-    _context: BaseType,
+    context: BaseType,
 ) -> FullType<'a> {
     match expression {
         Expression::Binary {
@@ -97,7 +248,7 @@ pub(crate) fn type_expression<'a, 'b>(
             op,
             op_span,
             operand,
-        } => type_unary_expression(typer, op, op_span, operand, flags),
+        } => type_unary_expression(typer, op, op_span, operand, flags, context),
         Expression::Subquery(select) => {
             let select_type = type_union_select(typer, select, false);
             if let [v] = select_type.columns.as_slice() {
@@ -119,13 +270,26 @@ pub(crate) fn type_expression<'a, 'b>(
         }
         Expression::Null(_) => FullType::new(Type::Null, false),
         Expression::Bool(_, _) => FullType::new(BaseType::Bool, true),
-        Expression::String(_) => FullType::new(BaseType::String, true),
+        Expression::String(v) => match context {
+            // String literals are implicitly converted to dates and times
+            BaseType::Date | BaseType::DateTime | BaseType::Time | BaseType::TimeStamp => {
+                if !is_temporal_literal(context, &v.value) {
+                    typer
+                        .issues
+                        .push(Issue::warn(format!("Not a valid {} literal", context), v));
+                }
+                FullType::new(context, true)
+            }
+            _ => FullType::new(BaseType::String, true),
+        },
         Expression::Integer(_) => FullType::new(BaseType::Integer, true),
         Expression::Float(_) => FullType::new(BaseType::Float, true),
-        Expression::Function(func, args, span) => type_function(typer, func, args, span, flags),
+        Expression::Function(func, args, span) => {
+            type_function(typer, func, args, span, flags, context)
+        }
         Expression::Identifier(i) => {
             let mut t = None;
-            match i.as_slice() {
+            match typer.strip_namespace(i) {
                 [part] => {
                     let col = match part {
                         sql_parse::IdentifierPart::Name(n) => n,
@@ -137,7 +301,7 @@ pub(crate) fn type_expression<'a, 'b>(
                     let mut cnt = 0;
                     for r in &mut typer.reference_types {
                         for c in &mut r.columns {
-                            if c.0 == col.value {
+                            if c.0 == col.value && !r.hidden.contains(&c.0) {
                                 cnt += 1;
                                 if flags.not_null {
                                     c.1.not_null = true;
@@ -150,7 +314,7 @@ pub(crate) fn type_expression<'a, 'b>(
                         let mut issue = Issue::err("Ambiguous reference", col);
                         for r in &typer.reference_types {
                             for c in &r.columns {
-                                if c.0 == col.value {
+                                if c.0 == col.value && !r.hidden.contains(&c.0) {
                                     issue = issue.frag("Defined here", &r.span);
                                 }
                             }
@@ -204,10 +368,15 @@ pub(crate) fn type_expression<'a, 'b>(
                 Some((_, type_)) => type_.clone(),
             }
         }
-        Expression::Arg((idx, span)) => FullType::new(
-            Type::Args(BaseType::Any, vec![(*idx, ArgType::Normal, span.clone())]),
-            false,
-        ),
+        Expression::Arg((idx, span)) => {
+            if context != BaseType::Any {
+                typer.constrain_arg(*idx, &ArgType::Normal, span, &FullType::new(context, false));
+            }
+            FullType::new(
+                Type::Args(context, vec![(*idx, ArgType::Normal, span.clone())]),
+                false,
+            )
+        }
         Expression::Exists(s) => {
             type_union_select(typer, s, false);
             FullType::new(BaseType::Bool, true)
@@ -239,7 +408,7 @@ pub(crate) fn type_expression<'a, 'b>(
                                 q,
                             ))
                         }
-                        if let Some(c) = rhs_type.columns.get(0) {
+                        if let Some(c) = rhs_type.columns.first() {
                             c.type_.clone()
                         } else {
                             FullType::invalid()
@@ -249,7 +418,7 @@ pub(crate) fn type_expression<'a, 'b>(
                         Type::Args(BaseType::Any, vec![(*idx, ArgType::ListHack, span.clone())]),
                         false,
                     ),
-                    _ => type_expression(typer, rhs, flags.without_values(), BaseType::Any),
+                    _ => type_expression(typer, rhs, flags.without_values(), lhs_type.base()),
                 };
                 not_null &= rhs_type.not_null;
                 if typer.matched_type(&lhs_type, &rhs_type).is_none() {
@@ -259,6 +428,7 @@ pub(crate) fn type_expression<'a, 'b>(
                             .frag(rhs_type.to_string(), rhs),
                     );
                 }
+                typer.ensure_enum_value(rhs, &lhs_type.t);
             }
             FullType::new(BaseType::Bool, not_null)
         }
@@ -300,10 +470,20 @@ pub(crate) fn type_expression<'a, 'b>(
             }
         }
         Expression::Invalid(_) => FullType::invalid(),
-        Expression::Case { .. } => {
-            typer.issues.push(issue_todo!(expression));
-            FullType::invalid()
-        }
+        Expression::Case {
+            case_span,
+            value,
+            whens,
+            else_,
+            ..
+        } => type_case_expression(
+            typer,
+            case_span,
+            value.as_deref(),
+            whens,
+            else_.as_ref().map(|(_, e)| e.deref()),
+            flags,
+        ),
         Expression::Cast {
             expr,
             as_span,
@@ -321,6 +501,7 @@ pub(crate) fn type_expression<'a, 'b>(
                     | sql_parse::Type::Float(_)
                     | sql_parse::Type::Integer(_)
                     | sql_parse::Type::Int(_)
+                    | sql_parse::Type::Numeric(_, _, _)
                     | sql_parse::Type::Binary(_)
                     | sql_parse::Type::Timestamptz
                     | sql_parse::Type::Time(_) => {}
@@ -335,7 +516,6 @@ pub(crate) fn type_expression<'a, 'b>(
                     | sql_parse::Type::LongText(_)
                     | sql_parse::Type::Enum(_)
                     | sql_parse::Type::Set(_)
-                    | sql_parse::Type::Numeric(_, _, _)
                     | sql_parse::Type::Timestamp(_)
                     | sql_parse::Type::TinyBlob(_)
                     | sql_parse::Type::MediumBlob(_)
@@ -367,7 +547,7 @@ pub(crate) fn type_expression<'a, 'b>(
                     type_expression(typer, arg, flags.without_values(), BaseType::Any);
                 }
             }
-            FullType::new(BaseType::Integer, true)
+            FullType::new(Type::I64, true)
         }
         Expression::GroupConcat { expr, .. } => {
             type_expression(typer, expr, flags.without_values(), BaseType::Any);
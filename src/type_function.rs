@@ -16,7 +16,7 @@ use sql_parse::{Expression, Function, Issue, Span};
 use crate::{
     type_::{BaseType, FullType},
     type_expression::{type_expression, ExpressionFlags},
-    typer::Typer,
+    typer::{decimal_digits, decimal_type, int_width, Typer},
     Type,
 };
 
@@ -59,25 +59,65 @@ fn typed_args<'a, 'b, 'c>(
     typer: &mut Typer<'a, 'b>,
     args: &'c [Expression<'a>],
     flags: ExpressionFlags,
+    context: BaseType,
 ) -> Vec<(&'c Expression<'a>, FullType<'a>)> {
     let mut typed: Vec<(&'_ Expression, FullType<'a>)> = Vec::new();
     for arg in args {
         // TODO we need not always disable the not null flag here
-        // TODO we should not supply base type any here, this function needs to die
         typed.push((
             arg,
-            type_expression(typer, arg, flags.without_values(), BaseType::Any),
+            type_expression(typer, arg, flags.without_values(), context),
         ));
     }
     typed
 }
 
+/// Type args that must all be of the same type as the result, where context
+/// is the expected type of the result. Returns the common type, and whether
+/// each argument is not null
+fn type_same_args<'a, 'b>(
+    typer: &mut Typer<'a, 'b>,
+    args: &[Expression<'a>],
+    flags: ExpressionFlags,
+    context: BaseType,
+    span: &Span,
+) -> (Option<Type<'a>>, Vec<bool>) {
+    let mut ans: Option<(&Expression<'a>, Type<'a>)> = None;
+    let mut not_null = Vec::new();
+    let mut arg_types = Vec::new();
+    for arg in args {
+        let context = ans.as_ref().map(|(_, t)| t.base()).unwrap_or(context);
+        let t = type_expression(typer, arg, flags.without_values(), context);
+        not_null.push(t.not_null);
+        arg_types.push(t.t.clone());
+        ans = match ans {
+            None => Some((arg, t.t)),
+            Some((e, at)) => match typer.matched_type(&at, &t) {
+                Some(mt) => Some((e, mt)),
+                None => {
+                    typer.issues.push(
+                        Issue::err("Incompatible types", span)
+                            .frag(format!("Of type {}", at), e)
+                            .frag(format!("Of type {}", t.t), arg),
+                    );
+                    Some((e, at))
+                }
+            },
+        };
+    }
+    if let Some((_, t)) = &ans {
+        typer.constrain_branch_args(&arg_types, t);
+    }
+    (ans.map(|(_, t)| t), not_null)
+}
+
 pub(crate) fn type_function<'a, 'b>(
     typer: &mut Typer<'a, 'b>,
     func: &Function<'a>,
     args: &[Expression<'a>],
     span: &Span,
     flags: ExpressionFlags,
+    context: BaseType,
 ) -> FullType<'a> {
     let mut tf = |return_type: Type<'a>,
                   required_args: &[BaseType],
@@ -143,38 +183,51 @@ pub(crate) fn type_function<'a, 'b>(
             &[BaseType::String, BaseType::String, BaseType::String],
             &[],
         ),
-        Function::CharacterLength => return tf(BaseType::Integer.into(), &[BaseType::String], &[]),
+        Function::CharacterLength => tf(BaseType::Integer.into(), &[BaseType::String], &[]),
         Function::UnixTimestamp => {
             let mut not_null = true;
-            let typed = typed_args(typer, args, flags);
             arg_cnt(typer, 0..1, args, span);
-            if let Some((a, t)) = typed.get(0) {
+            for a in args {
+                let t = type_expression(typer, a, flags.without_values(), BaseType::DateTime);
                 not_null = not_null && t.not_null;
-                // TODO the argument can be both a DATE, a DATE_TIME or a TIMESTAMP
-                typer.ensure_base(*a, t, BaseType::DateTime);
+                // Dates and timestamps are converted to date times
+                typer.ensure_base(a, &t, BaseType::DateTime);
             }
             FullType::new(Type::I64, not_null)
         }
         Function::IfNull => {
-            let typed = typed_args(typer, args, flags);
             arg_cnt(typer, 2..2, args, span);
-            let t = if let Some((e, t)) = typed.get(0) {
+            let t = if let Some(e) = args.first() {
+                let t = type_expression(typer, e, flags.without_values(), context);
                 if t.not_null {
-                    typer.issues.push(Issue::warn("Cannot be null", *e));
+                    typer.issues.push(Issue::warn("Cannot be null", e));
                 }
-                t.clone()
+                t
             } else {
                 FullType::invalid()
             };
-            if let Some((e, t2)) = typed.get(1) {
-                typer.ensure_type(*e, t2, &t);
-                t2.clone()
+            if let Some(e) = args.get(1) {
+                let context = if t.base() == BaseType::Any {
+                    context
+                } else {
+                    t.base()
+                };
+                let t2 = type_expression(typer, e, flags.without_values(), context);
+                match typer.matched_type(&t2, &t) {
+                    Some(mt) => FullType::new(mt, t2.not_null),
+                    None => {
+                        typer
+                            .issues
+                            .push(Issue::err(format!("Expected type {} got {}", t.t, t2.t), e));
+                        t2
+                    }
+                }
             } else {
-                t.clone()
+                t
             }
         }
         Function::JsonExtract => {
-            let typed = typed_args(typer, args, flags);
+            let typed = typed_args(typer, args, flags, BaseType::Any);
             arg_cnt(typer, 2..999, args, span);
             for (a, t) in &typed {
                 typer.ensure_base(*a, t, BaseType::String);
@@ -182,7 +235,7 @@ pub(crate) fn type_function<'a, 'b>(
             FullType::new(Type::JSON, false)
         }
         Function::JsonValue => {
-            let typed = typed_args(typer, args, flags);
+            let typed = typed_args(typer, args, flags, BaseType::Any);
             arg_cnt(typer, 2..2, args, span);
             for (a, t) in &typed {
                 typer.ensure_base(*a, t, BaseType::String);
@@ -190,7 +243,7 @@ pub(crate) fn type_function<'a, 'b>(
             FullType::new(Type::JSON, false)
         }
         Function::JsonReplace => {
-            let typed = typed_args(typer, args, flags);
+            let typed = typed_args(typer, args, flags, BaseType::Any);
             arg_cnt(typer, 3..999, args, span);
             for (i, (a, t)) in typed.iter().enumerate() {
                 if i == 0 || i % 2 == 1 {
@@ -200,7 +253,7 @@ pub(crate) fn type_function<'a, 'b>(
             FullType::new(Type::JSON, false)
         }
         Function::JsonSet => {
-            let typed = typed_args(typer, args, flags);
+            let typed = typed_args(typer, args, flags, BaseType::Any);
             arg_cnt(typer, 3..999, args, span);
             for (i, (a, t)) in typed.iter().enumerate() {
                 if i == 0 || i % 2 == 1 {
@@ -210,17 +263,55 @@ pub(crate) fn type_function<'a, 'b>(
             FullType::new(Type::JSON, false)
         }
         Function::JsonUnquote => {
-            let typed = typed_args(typer, args, flags);
+            let typed = typed_args(typer, args, flags, BaseType::Any);
             arg_cnt(typer, 1..1, args, span);
             for (a, t) in &typed {
                 typer.ensure_base(*a, t, BaseType::String);
             }
             FullType::new(BaseType::String, false)
         }
-        Function::Min | Function::Max | Function::Sum => {
-            let typed = typed_args(typer, args, flags);
+        Function::Sum => {
+            let typed = typed_args(typer, args, flags, BaseType::Any);
             arg_cnt(typer, 1..1, args, span);
-            if let Some((_, t2)) = typed.get(0) {
+            if let Some((a, t)) = typed.first() {
+                // The sum of integers or decimals is a decimal with 22 extra digits of
+                // precision in mariadb. Postgresql sums integers of up to 32 bits
+                // to a bigint, and other integers and decimals to an unconstrained numeric.
+                // Result can be null if there are no rows to aggregate over
+                match t.base() {
+                    BaseType::Float => FullType::new(
+                        if t.t == Type::F32 && typer.dialect().is_postgresql() {
+                            Type::F32
+                        } else {
+                            Type::F64
+                        },
+                        false,
+                    ),
+                    BaseType::Integer if typer.dialect().is_postgresql() => match int_width(&t.t) {
+                        Some((_, 64)) => FullType::new(BaseType::Decimal, false),
+                        _ => FullType::new(Type::I64, false),
+                    },
+                    _ => {
+                        typer.ensure_base(*a, t, BaseType::Decimal);
+                        match decimal_digits(&t.t) {
+                            Some((precision, scale)) if !typer.dialect().is_postgresql() => {
+                                FullType::new(
+                                    decimal_type(typer.dialect(), precision + 22, scale),
+                                    false,
+                                )
+                            }
+                            _ => FullType::new(BaseType::Decimal, false),
+                        }
+                    }
+                }
+            } else {
+                FullType::invalid()
+            }
+        }
+        Function::Min | Function::Max => {
+            let typed = typed_args(typer, args, flags, context);
+            arg_cnt(typer, 1..1, args, span);
+            if let Some((_, t2)) = typed.first() {
                 // TODO check that the type can be mined or maxed
                 // Result can be null if there are no rows to aggregate over
                 let mut v = t2.clone();
@@ -230,13 +321,34 @@ pub(crate) fn type_function<'a, 'b>(
                 FullType::invalid()
             }
         }
-        Function::Now => return tf(BaseType::DateTime.into(), &[], &[BaseType::Integer]),
-        Function::CurDate => return tf(BaseType::Date.into(), &[], &[]),
-        Function::CurrentTimestamp => {
-            return tf(BaseType::TimeStamp.into(), &[], &[BaseType::Integer])
+        Function::Other(name) if name.eq_ignore_ascii_case("avg") => {
+            let typed = typed_args(typer, args, flags, BaseType::Any);
+            arg_cnt(typer, 1..1, args, span);
+            if let Some((a, t)) = typed.first() {
+                // The average of integers or decimals is a decimal with
+                // div_precision_increment (4) extra digits of scale.
+                // Result can be null if there are no rows to aggregate over
+                if t.base() == BaseType::Float {
+                    FullType::new(Type::F64, false)
+                } else {
+                    typer.ensure_base(*a, t, BaseType::Decimal);
+                    match decimal_digits(&t.t) {
+                        Some((precision, scale)) => FullType::new(
+                            decimal_type(typer.dialect(), precision + 4, scale + 4),
+                            false,
+                        ),
+                        None => FullType::new(BaseType::Decimal, false),
+                    }
+                }
+            } else {
+                FullType::invalid()
+            }
         }
+        Function::Now => tf(BaseType::DateTime.into(), &[], &[BaseType::Integer]),
+        Function::CurDate => tf(BaseType::Date.into(), &[], &[]),
+        Function::CurrentTimestamp => tf(BaseType::TimeStamp.into(), &[], &[BaseType::Integer]),
         Function::Concat => {
-            let typed = typed_args(typer, args, flags);
+            let typed = typed_args(typer, args, flags, BaseType::Any);
             let mut not_null = true;
             for (_, t) in typed {
                 not_null = not_null && t.not_null;
@@ -244,9 +356,9 @@ pub(crate) fn type_function<'a, 'b>(
             FullType::new(BaseType::String, not_null)
         }
         Function::Least | Function::Greatest => {
-            let typed = typed_args(typer, args, flags);
+            let typed = typed_args(typer, args, flags, context);
             arg_cnt(typer, 1..9999, args, span);
-            if let Some((a, at)) = typed.get(0) {
+            if let Some((a, at)) = typed.first() {
                 let mut not_null = true;
                 let mut t = at.t.clone();
                 for (b, bt) in &typed[1..] {
@@ -269,36 +381,34 @@ pub(crate) fn type_function<'a, 'b>(
             FullType::new(BaseType::Any, true)
         }
         Function::If => {
-            let typed = typed_args(typer, args, flags);
             arg_cnt(typer, 3..3, args, span);
             let mut not_null = true;
-            if let Some((e, t)) = typed.get(0) {
+            if let Some(e) = args.first() {
+                let t = type_expression(typer, e, flags.without_values(), BaseType::Bool);
                 not_null = not_null && t.not_null;
-                typer.ensure_base(*e, t, BaseType::Bool);
+                typer.ensure_base(e, &t, BaseType::Bool);
             }
-            let mut ans = FullType::invalid();
-            if let Some((e1, t1)) = typed.get(1) {
-                not_null = not_null && t1.not_null;
-                if let Some((e2, t2)) = typed.get(2) {
-                    not_null = not_null && t2.not_null;
-                    if let Some(t) = typer.matched_type(t1, t2) {
-                        ans = FullType::new(t, not_null);
-                    } else {
-                        typer.issues.push(
-                            Issue::err("Incompatible types", span)
-                                .frag(format!("Of type {}", t1.t), *e1)
-                                .frag(format!("Of type {}", t2.t), *e2),
-                        );
-                    }
+            let branches = args.get(1..).unwrap_or_default();
+            match type_same_args(typer, branches, flags, context, span) {
+                (Some(t), nn) if args.len() == 3 => {
+                    FullType::new(t, not_null && nn.iter().all(|v| *v))
                 }
+                _ => FullType::invalid(),
+            }
+        }
+        Function::Other(name) if name.eq_ignore_ascii_case("coalesce") => {
+            arg_cnt(typer, 1..9999, args, span);
+            match type_same_args(typer, args, flags, context, span) {
+                // The result is only null if all arguments are
+                (Some(t), nn) => FullType::new(t, nn.iter().any(|v| *v)),
+                (None, _) => FullType::invalid(),
             }
-            ans
         }
         Function::FromUnixTime => {
-            let typed = typed_args(typer, args, flags);
+            let typed = typed_args(typer, args, flags, BaseType::Any);
             arg_cnt(typer, 1..2, args, span);
             let mut not_null = true;
-            if let Some((e, t)) = typed.get(0) {
+            if let Some((e, t)) = typed.first() {
                 not_null = not_null && t.not_null;
                 // TODO float og int
                 typer.ensure_base(*e, t, BaseType::Float);
@@ -311,9 +421,13 @@ pub(crate) fn type_function<'a, 'b>(
                 FullType::new(BaseType::DateTime, not_null)
             }
         }
-        Function::DateFormat => return tf(BaseType::String.into(), &[BaseType::DateTime, BaseType::String], &[BaseType::String]),
+        Function::DateFormat => tf(
+            BaseType::String.into(),
+            &[BaseType::DateTime, BaseType::String],
+            &[BaseType::String],
+        ),
         Function::Value => {
-            let typed = typed_args(typer, args, flags);
+            let typed = typed_args(typer, args, flags, BaseType::Any);
             if !flags.in_on_duplicate_key_update {
                 typer.issues.push(Issue::err(
                     "VALUE is only allowed within ON DUPLICATE KEY UPDATE",
@@ -321,12 +435,52 @@ pub(crate) fn type_function<'a, 'b>(
                 ));
             }
             arg_cnt(typer, 1..1, args, span);
-            if let Some((_, t)) = typed.get(0) {
+            if let Some((_, t)) = typed.first() {
                 t.clone()
             } else {
                 FullType::invalid()
             }
         }
+        Function::Other(name) => {
+            let schemas = typer.schemas;
+            let function = schemas
+                .functions
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name));
+            let function = match function {
+                Some((_, f)) => f,
+                None => {
+                    typer.issues.push(Issue::err("Unknown function", span));
+                    for arg in args {
+                        type_expression(typer, arg, flags.without_values(), BaseType::Any);
+                    }
+                    return FullType::invalid();
+                }
+            };
+            let cnt = function.params.len();
+            arg_cnt(typer, cnt..cnt, args, span);
+            let mut arg_iter = args.iter();
+            for (_, et) in &function.params {
+                if let Some(arg) = arg_iter.next() {
+                    let t = type_expression(typer, arg, flags.without_values(), et.base());
+                    if typer.matched_type(&t, et).is_none() {
+                        typer.issues.push(Issue::err(
+                            format!("Got type {} expected {}", t.t, et.t),
+                            arg,
+                        ));
+                    } else if let Type::Args(_, args) = &t.t {
+                        for (idx, arg_type, span) in args {
+                            typer.constrain_arg(*idx, arg_type, span, et);
+                        }
+                    }
+                }
+            }
+            for arg in arg_iter {
+                type_expression(typer, arg, flags.without_values(), BaseType::Any);
+            }
+            // Stored functions may return null whatever their arguments
+            FullType::new(function.return_type.t.clone(), false)
+        }
         _ => {
             typer
                 .issues
@@ -0,0 +1,356 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{format, vec::Vec};
+use core::mem::discriminant;
+use sql_parse::{Expression, Function, IdentifierPart, Issue, OptSpanned, Select, Span, Spanned};
+
+use crate::{
+    typer::{resolve_column, ReferenceType, Typer},
+    TypeOptions,
+};
+
+fn is_aggregate(expression: &Expression<'_>) -> bool {
+    match expression {
+        Expression::Count { .. } | Expression::GroupConcat { .. } => true,
+        Expression::Function(
+            Function::Min
+            | Function::Max
+            | Function::Sum
+            | Function::JsonArrayAgg
+            | Function::JsonObjectAgg,
+            _,
+            _,
+        ) => true,
+        Expression::Function(Function::Other(name), _, _) => [
+            "avg",
+            "bit_and",
+            "bit_or",
+            "bit_xor",
+            "std",
+            "stddev",
+            "stddev_pop",
+            "stddev_samp",
+            "var_pop",
+            "var_samp",
+            "variance",
+        ]
+        .iter()
+        .any(|v| name.eq_ignore_ascii_case(v)),
+        _ => false,
+    }
+}
+
+/// Visit expression and its sub expressions, not descending into sub queries,
+/// or into expressions for which cb returns false
+fn visit<'c, 'a>(expression: &'c Expression<'a>, cb: &mut impl FnMut(&'c Expression<'a>) -> bool) {
+    if !cb(expression) {
+        return;
+    }
+    match expression {
+        Expression::Binary { lhs, rhs, .. } => {
+            visit(lhs, cb);
+            visit(rhs, cb);
+        }
+        Expression::Unary { operand, .. } => visit(operand, cb),
+        Expression::Function(_, args, _) => {
+            for arg in args {
+                visit(arg, cb);
+            }
+        }
+        Expression::In { lhs, rhs, .. } => {
+            visit(lhs, cb);
+            for v in rhs {
+                visit(v, cb);
+            }
+        }
+        Expression::Is(e, _, _) => visit(e, cb),
+        Expression::Case {
+            value,
+            whens,
+            else_,
+            ..
+        } => {
+            if let Some(value) = value {
+                visit(value, cb);
+            }
+            for when in whens {
+                visit(&when.when, cb);
+                visit(&when.then, cb);
+            }
+            if let Some((_, else_)) = else_ {
+                visit(else_, cb);
+            }
+        }
+        Expression::Cast { expr, .. }
+        | Expression::Count { expr, .. }
+        | Expression::GroupConcat { expr, .. } => visit(expr, cb),
+        Expression::Subquery(_)
+        | Expression::Exists(_)
+        | Expression::Null(_)
+        | Expression::Bool(_, _)
+        | Expression::String(_)
+        | Expression::Integer(_)
+        | Expression::ListHack(_)
+        | Expression::Float(_)
+        | Expression::Identifier(_)
+        | Expression::Arg(_)
+        | Expression::Invalid(_) => (),
+    }
+}
+
+fn find_aggregate(expression: &Expression<'_>) -> Option<Span> {
+    let mut ans = None;
+    visit(expression, &mut |e| {
+        if ans.is_some() {
+            return false;
+        }
+        if is_aggregate(e) {
+            ans = Some(e.span());
+            return false;
+        }
+        true
+    });
+    ans
+}
+
+/// Column references in expression that are not inside an aggregate or
+/// a sub expression for which grouped returns true
+fn plain_columns<'c, 'a>(
+    expression: &'c Expression<'a>,
+    grouped: impl Fn(&Expression<'a>) -> bool,
+) -> Vec<&'c [IdentifierPart<'a>]> {
+    let mut ans = Vec::new();
+    visit(expression, &mut |e| match e {
+        e if grouped(e) => false,
+        Expression::Identifier(parts) => {
+            ans.push(parts.as_slice());
+            false
+        }
+        e => !is_aggregate(e),
+    });
+    ans
+}
+
+fn same_function(f1: &Function<'_>, f2: &Function<'_>) -> bool {
+    match (f1, f2) {
+        (Function::Other(n1), Function::Other(n2)) => n1.eq_ignore_ascii_case(n2),
+        (f1, f2) => discriminant(f1) == discriminant(f2),
+    }
+}
+
+/// Are the expressions the same, up to how columns are qualified
+fn same_expression<'a>(
+    options: &TypeOptions,
+    refs: &[ReferenceType<'a>],
+    e1: &Expression<'a>,
+    e2: &Expression<'a>,
+) -> bool {
+    let same = |e1, e2| same_expression(options, refs, e1, e2);
+    match (e1, e2) {
+        (
+            Expression::Binary {
+                op: o1,
+                lhs: l1,
+                rhs: r1,
+                ..
+            },
+            Expression::Binary {
+                op: o2,
+                lhs: l2,
+                rhs: r2,
+                ..
+            },
+        ) => discriminant(o1) == discriminant(o2) && same(l1, l2) && same(r1, r2),
+        (
+            Expression::Unary {
+                op: o1,
+                operand: v1,
+                ..
+            },
+            Expression::Unary {
+                op: o2,
+                operand: v2,
+                ..
+            },
+        ) => discriminant(o1) == discriminant(o2) && same(v1, v2),
+        (Expression::Function(f1, a1, _), Expression::Function(f2, a2, _)) => {
+            same_function(f1, f2)
+                && a1.len() == a2.len()
+                && a1.iter().zip(a2).all(|(a, b)| same(a, b))
+        }
+        (Expression::Is(v1, i1, _), Expression::Is(v2, i2, _)) => {
+            discriminant(i1) == discriminant(i2) && same(v1, v2)
+        }
+        (Expression::Identifier(p1), Expression::Identifier(p2)) => {
+            match (
+                resolve_column(options, refs, p1),
+                resolve_column(options, refs, p2),
+            ) {
+                (Some(c1), Some(c2)) => c1 == c2,
+                _ => false,
+            }
+        }
+        (Expression::Null(_), Expression::Null(_)) => true,
+        (Expression::Bool(v1, _), Expression::Bool(v2, _)) => v1 == v2,
+        (Expression::String(v1), Expression::String(v2)) => v1.value == v2.value,
+        (Expression::Integer((v1, _)), Expression::Integer((v2, _))) => v1 == v2,
+        (Expression::Float((v1, _)), Expression::Float((v2, _))) => v1 == v2,
+        _ => false,
+    }
+}
+
+/// Check the select the way MariaDB and PostgreSQL do with ONLY_FULL_GROUP_BY,
+/// refs are the references introduced by the FROM clause of the select
+pub(crate) fn check_group_by<'a, 'b>(
+    typer: &mut Typer<'a, 'b>,
+    select: &Select<'a>,
+    refs: &[ReferenceType<'a>],
+) {
+    if let Some((where_, _)) = &select.where_ {
+        if let Some(span) = find_aggregate(where_) {
+            typer
+                .issues
+                .push(Issue::err("Aggregates are not allowed in WHERE", &span));
+        }
+    }
+
+    let group_by = select.group_by.as_ref().map(|(_, v)| v.as_slice());
+    let aggregated = select
+        .select_exprs
+        .iter()
+        .any(|e| find_aggregate(&e.expr).is_some())
+        || select
+            .having
+            .as_ref()
+            .is_some_and(|(h, _)| find_aggregate(h).is_some());
+    if group_by.is_none() && !aggregated {
+        return;
+    }
+
+    let mut grouped = Vec::new();
+    let mut grouped_aliases = Vec::new();
+    let mut grouped_exprs = Vec::new();
+    for e in group_by.unwrap_or_default() {
+        // Integers give the position of a select expression to group by
+        let e = match e {
+            Expression::Integer((v, span)) => match usize::try_from(*v)
+                .ok()
+                .and_then(|v| v.checked_sub(1))
+                .and_then(|i| select.select_exprs.get(i))
+            {
+                Some(se) => &se.expr,
+                None => {
+                    typer
+                        .issues
+                        .push(Issue::err("Unknown column position in GROUP BY", span));
+                    continue;
+                }
+            },
+            e => e,
+        };
+        grouped_exprs.push(e);
+        if let Expression::Identifier(parts) = e {
+            match (resolve_column(typer.options, refs, parts), parts.as_slice()) {
+                (Some(v), _) => grouped.push(v),
+                (None, [IdentifierPart::Name(n)]) => grouped_aliases.push(n.value),
+                _ => (),
+            }
+        }
+    }
+
+    // All columns of a reference are functionally dependent on its keys
+    for (i, r) in refs.iter().enumerate() {
+        if r.keys
+            .iter()
+            .any(|k| k.iter().all(|c| grouped.contains(&(i, *c))))
+        {
+            for c in &r.columns {
+                grouped.push((i, c.0));
+            }
+        }
+    }
+
+    let options = typer.options;
+    let is_grouped = |e: &Expression<'a>| {
+        !matches!(e, Expression::Identifier(_))
+            && grouped_exprs
+                .iter()
+                .any(|g| same_expression(options, refs, g, e))
+    };
+
+    let check = |typer: &mut Typer<'a, 'b>, parts: &[IdentifierPart<'a>]| {
+        if let Some(col) = resolve_column(typer.options, refs, parts) {
+            if !grouped.contains(&col) {
+                typer.issues.push(Issue::err(
+                    format!("'{}' is not in GROUP BY nor in an aggregate", col.1),
+                    &parts.opt_span().expect("identifier span"),
+                ));
+            }
+        }
+    };
+
+    for e in &select.select_exprs {
+        if e.as_
+            .as_ref()
+            .is_some_and(|a| grouped_aliases.contains(&a.value))
+        {
+            continue;
+        }
+        match &e.expr {
+            Expression::Identifier(parts)
+                if matches!(parts.last(), Some(IdentifierPart::Star(_))) =>
+            {
+                let tbl = match parts.as_slice() {
+                    [.., IdentifierPart::Name(tbl), _] => Some(tbl.value),
+                    _ => None,
+                };
+                for (i, r) in refs.iter().enumerate() {
+                    if tbl.is_some() && r.name != tbl {
+                        continue;
+                    }
+                    for c in &r.columns {
+                        if (tbl.is_none() && r.hidden.contains(&c.0)) || grouped.contains(&(i, c.0))
+                        {
+                            continue;
+                        }
+                        typer.issues.push(Issue::err(
+                            format!("'{}' is not in GROUP BY nor in an aggregate", c.0),
+                            &parts.opt_span().expect("identifier span"),
+                        ));
+                    }
+                }
+            }
+            expr => {
+                for parts in plain_columns(expr, is_grouped) {
+                    check(typer, parts);
+                }
+            }
+        }
+    }
+
+    if let Some((having, _)) = &select.having {
+        let aliases: Vec<_> = select
+            .select_exprs
+            .iter()
+            .filter_map(|e| e.as_.as_ref().map(|a| a.value))
+            .collect();
+        for parts in plain_columns(having, is_grouped) {
+            if let [IdentifierPart::Name(n)] = parts {
+                if aliases.contains(&n.value) {
+                    continue;
+                }
+            }
+            check(typer, parts);
+        }
+    }
+}
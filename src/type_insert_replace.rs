@@ -11,7 +11,9 @@
 // limitations under the License.
 
 use alloc::{format, vec::Vec};
-use sql_parse::{issue_todo, InsertReplace, InsertReplaceFlag, InsertReplaceType, Issue, Spanned};
+use sql_parse::{
+    issue_todo, InsertReplace, InsertReplaceFlag, InsertReplaceType, Issue, OptSpanned, Spanned,
+};
 
 use crate::{
     type_expression::{type_expression, ExpressionFlags},
@@ -35,14 +37,10 @@ pub(crate) fn type_insert_replace<'a, 'b>(
     let table = &ior.table;
     let columns = &ior.columns;
 
-    if let Some(v) = table.get(1..) {
-        for t in v {
-            typer.issues.push(issue_todo!(t));
-        }
-    }
-
-    let t = &table[0];
-    let (s, auto_increment) = if let Some(schema) = typer.schemas.schemas.get(t.value) {
+    let t = table.last().expect("table name");
+    let namespace = typer.other_namespace(table);
+    let schema = typer.get_schema(table);
+    let (s, auto_increment) = if let Some(schema) = schema {
         if schema.view {
             typer
                 .issues
@@ -52,35 +50,86 @@ pub(crate) fn type_insert_replace<'a, 'b>(
 
         for col in columns {
             if let Some(schema_col) = schema.get_column(col.value) {
-                col_types.push((schema_col.type_.ref_clone(), col.span()));
+                typer.ensure_writable(schema, col);
+                col_types.push((schema_col.type_.clone(), col.span()));
             } else {
                 typer
                     .issues
                     .push(Issue::err("No such column in schema", col));
             }
         }
+        // Without a column list values are given for all columns in order
+        if columns.is_empty() && ior.set.is_none() {
+            for c in &schema.columns {
+                col_types.push((c.type_.clone(), t.span()));
+            }
+        }
+        let given: Vec<_> = if let Some((_, set)) = &ior.set {
+            set.iter().map(|(key, _, _)| key.value).collect()
+        } else {
+            columns.iter().map(|c| c.value).collect()
+        };
+        // Without a column list values are given for all columns
+        if !given.is_empty() {
+            let missing: Vec<_> = schema
+                .columns
+                .iter()
+                .filter(|c| {
+                    c.type_.not_null
+                        && !c.default
+                        && !c.auto_increment
+                        && !c.generated
+                        && !given.contains(&c.identifier)
+                })
+                .map(|c| c.identifier)
+                .collect();
+            if !missing.is_empty() {
+                typer.issues.push(Issue::err(
+                    format!(
+                        "No value given for NOT NULL column without default: {}",
+                        missing.join(", ")
+                    ),
+                    t,
+                ));
+            }
+        }
         (
             Some(col_types),
             schema.columns.iter().any(|c| c.auto_increment),
         )
     } else {
-        typer.issues.push(Issue::err("Unknown table", t));
         (None, false)
     };
 
     if let Some(values) = &ior.values {
+        let width = schema.map(|s| {
+            if columns.is_empty() {
+                s.columns.len()
+            } else {
+                columns.len()
+            }
+        });
         for row in &values.1 {
+            if let Some(width) = width {
+                if row.len() != width {
+                    typer.issues.push(Issue::err(
+                        format!("Expected {} values got {}", width, row.len()),
+                        &row.opt_span().unwrap_or_else(|| values.0.clone()),
+                    ));
+                }
+            }
             for (j, e) in row.iter().enumerate() {
                 if let Some((et, ets)) = s.as_ref().and_then(|v| v.get(j)) {
                     let t = type_expression(typer, e, ExpressionFlags::default(), et.base());
+                    typer.ensure_enum_value(e, &et.t);
                     if typer.matched_type(&t, et).is_none() {
                         typer.issues.push(
                             Issue::err(format!("Got type {}", t.t), e)
                                 .frag(format!("Expected {}", et.t), ets),
                         );
                     } else if let Type::Args(_, args) = &t.t {
-                        for (idx, arg_type, _) in args {
-                            typer.constrain_arg(*idx, arg_type, et);
+                        for (idx, arg_type, span) in args {
+                            typer.constrain_arg(*idx, arg_type, span, et);
                         }
                     }
                 } else {
@@ -101,6 +150,10 @@ pub(crate) fn type_insert_replace<'a, 'b>(
                                 Issue::err(format!("Got type {}", t.type_.t), &t.span)
                                     .frag(format!("Expected {}", et.t), ets),
                             );
+                        } else if let Type::Args(_, args) = &t.type_.t {
+                            for (idx, arg_type, span) in args {
+                                typer.constrain_arg(*idx, arg_type, span, et);
+                            }
                         }
                     }
                     (None, Some(t)) => {
@@ -128,10 +181,10 @@ pub(crate) fn type_insert_replace<'a, 'b>(
     );
     let typer = &mut guard.typer;
 
-    if let Some(s) = typer.schemas.schemas.get(t.value) {
+    if let Some(s) = schema {
         let mut columns = Vec::new();
         for c in &s.columns {
-            columns.push((c.identifier, c.type_.ref_clone()));
+            columns.push((c.identifier, c.type_.clone()));
         }
         for v in &typer.reference_types {
             if v.name == Some(t.value) {
@@ -142,13 +195,23 @@ pub(crate) fn type_insert_replace<'a, 'b>(
         }
         typer.reference_types.push(ReferenceType {
             name: Some(t.value),
+            table: match namespace {
+                None => Some(t.value),
+                Some(_) => None,
+            },
+            namespace,
             span: t.span(),
             columns,
+            hidden: Vec::new(),
+            keys: Vec::new(),
         });
     }
 
     if let Some((_, set)) = &ior.set {
         for (key, _, value) in set {
+            if let Some(schema) = schema {
+                typer.ensure_writable(schema, key);
+            }
             let mut cnt = 0;
             let mut t = None;
             for r in &typer.reference_types {
@@ -173,14 +236,15 @@ pub(crate) fn type_insert_replace<'a, 'b>(
             } else if let Some(t) = t {
                 let value_type =
                     type_expression(typer, value, ExpressionFlags::default(), t.1.base());
+                typer.ensure_enum_value(value, &t.1.t);
                 if typer.matched_type(&value_type, &t.1).is_none() {
                     typer.issues.push(Issue::err(
                         format!("Got type {} expected {}", value_type, t.1),
                         value,
                     ));
                 } else if let Type::Args(_, args) = &value_type.t {
-                    for (idx, arg_type, _) in args {
-                        typer.constrain_arg(*idx, arg_type, &t.1);
+                    for (idx, arg_type, span) in args {
+                        typer.constrain_arg(*idx, arg_type, span, &t.1);
                     }
                 }
             } else {
@@ -192,6 +256,9 @@ pub(crate) fn type_insert_replace<'a, 'b>(
 
     if let Some((_, update)) = &ior.on_duplicate_key_update {
         for (key, _, value) in update {
+            if let Some(schema) = schema {
+                typer.ensure_writable(schema, key);
+            }
             let mut cnt = 0;
             let mut t = None;
             for r in &typer.reference_types {
@@ -216,14 +283,15 @@ pub(crate) fn type_insert_replace<'a, 'b>(
                 typer.issues.push(issue);
             } else if let Some(t) = t {
                 let value_type = type_expression(typer, value, flags, t.1.base());
+                typer.ensure_enum_value(value, &t.1.t);
                 if typer.matched_type(&value_type, &t.1).is_none() {
                     typer.issues.push(Issue::err(
                         format!("Got type {} expected {}", value_type, t.1),
                         value,
                     ));
                 } else if let Type::Args(_, args) = &value_type.t {
-                    for (idx, arg_type, _) in args {
-                        typer.constrain_arg(*idx, arg_type, &t.1);
+                    for (idx, arg_type, span) in args {
+                        typer.constrain_arg(*idx, arg_type, span, &t.1);
                     }
                 }
             } else {
@@ -246,8 +314,15 @@ pub(crate) fn type_insert_replace<'a, 'b>(
                 }
                 if t.is_none() {
                     typer.issues.push(Issue::err("Unknown identifier", name));
+                } else if let Some(schema) = schema {
+                    // Only keys known from the schema definition are considered
+                    if !schema.is_unique(&[name.value]) {
+                        typer.issues.push(Issue::warn(
+                            "No unique key or constraint on column for ON CONFLICT",
+                            name,
+                        ));
+                    }
                 }
-                //TODO check if there is a unique constraint on column
             }
             sql_parse::OnConflictTarget::OnConstraint {
                 on_constraint_span, ..
@@ -261,6 +336,9 @@ pub(crate) fn type_insert_replace<'a, 'b>(
             sql_parse::OnConflictAction::DoNothing(_) => (),
             sql_parse::OnConflictAction::DoUpdateSet { sets, where_, .. } => {
                 for (key, value) in sets {
+                    if let Some(schema) = schema {
+                        typer.ensure_writable(schema, key);
+                    }
                     let mut cnt = 0;
                     let mut t = None;
                     for r in &typer.reference_types {
@@ -285,14 +363,15 @@ pub(crate) fn type_insert_replace<'a, 'b>(
                         typer.issues.push(issue);
                     } else if let Some(t) = t {
                         let value_type = type_expression(typer, value, flags, t.1.base());
+                        typer.ensure_enum_value(value, &t.1.t);
                         if typer.matched_type(&value_type, &t.1).is_none() {
                             typer.issues.push(Issue::err(
                                 format!("Got type {} expected {}", value_type, t.1),
                                 value,
                             ));
                         } else if let Type::Args(_, args) = &value_type.t {
-                            for (idx, arg_type, _) in args {
-                                typer.constrain_arg(*idx, arg_type, &t.1);
+                            for (idx, arg_type, span) in args {
+                                typer.constrain_arg(*idx, arg_type, span, &t.1);
                             }
                         }
                     } else {
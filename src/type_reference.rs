@@ -11,13 +11,184 @@
 // limitations under the License.
 
 use crate::{
-    type_::BaseType,
+    schema::IndexKind,
+    type_::{BaseType, FullType},
     type_expression::{type_expression, ExpressionFlags},
     type_select::type_union_select,
-    typer::{ReferenceType, Typer},
+    typer::{resolve_column, ReferenceType, Typer},
+};
+use alloc::{format, string::ToString, vec::Vec};
+use sql_parse::{
+    BinaryOperator, Expression, Identifier, Issue, JoinType, OptSpanned, Span, Spanned,
+    TableReference,
 };
-use alloc::vec::Vec;
-use sql_parse::{issue_todo, Issue, OptSpanned, Spanned, TableReference};
+
+/// Find the unique visible column named `col` among `refs` and hide it
+fn take_using_column<'a>(
+    issues: &mut Vec<Issue>,
+    refs: &mut [ReferenceType<'a>],
+    col: &Identifier<'a>,
+    side: &str,
+) -> Option<(FullType<'a>, Span)> {
+    let mut found = None;
+    let mut cnt = 0;
+    for (i, r) in refs.iter().enumerate() {
+        for c in &r.columns {
+            if c.0 == col.value && !r.hidden.contains(&c.0) {
+                cnt += 1;
+                found = Some((i, c.1.clone()));
+            }
+        }
+    }
+    if cnt > 1 {
+        let mut issue = Issue::err("Ambiguous reference", col);
+        for r in refs.iter() {
+            for c in &r.columns {
+                if c.0 == col.value && !r.hidden.contains(&c.0) {
+                    issue = issue.frag("Defined here", &r.span);
+                }
+            }
+        }
+        issues.push(issue);
+        return None;
+    }
+    let (i, t) = match found {
+        Some(v) => v,
+        None => {
+            issues.push(Issue::err(
+                format!("Unknown column on {} side of join", side),
+                col,
+            ));
+            return None;
+        }
+    };
+    let r = &mut refs[i];
+    r.hidden.push(col.value);
+    Some((t, r.span.clone()))
+}
+
+/// Check the columns of a USING clause, and merge each pair into a single
+/// unqualified column placed first in the join
+fn type_join_using<'a, 'b>(
+    typer: &mut Typer<'a, 'b>,
+    join: &JoinType,
+    cols: &[Identifier<'a>],
+    span: &Span,
+    left_cnt: usize,
+) {
+    let mut columns = Vec::new();
+    for col in cols {
+        if columns.iter().any(|(n, _)| *n == col.value) {
+            typer
+                .issues
+                .push(Issue::err("Column given multiple times", col));
+            continue;
+        }
+        let (left_refs, right_refs) = typer.reference_types.split_at_mut(left_cnt);
+        let l = take_using_column(typer.issues, left_refs, col, "left");
+        let r = take_using_column(typer.issues, right_refs, col, "right");
+        let ((lt, ls), (rt, rs)) = match (l, r) {
+            (Some(l), Some(r)) => (l, r),
+            _ => continue,
+        };
+        let t = match typer.matched_type(&lt, &rt) {
+            Some(t) => t,
+            None => {
+                typer.issues.push(
+                    Issue::err("Incompatible types in join", col)
+                        .frag(format!("Of type {}", lt.t), &ls)
+                        .frag(format!("Of type {}", rt.t), &rs),
+                );
+                continue;
+            }
+        };
+        // The merged value is taken from the side that is always present
+        let type_ = match join {
+            JoinType::Left(_) | JoinType::NaturalLeft(_) => lt,
+            JoinType::Right(_) | JoinType::NaturalRight(_) => rt,
+            _ => FullType::new(t, lt.not_null || rt.not_null),
+        };
+        columns.push((col.value, type_));
+    }
+    typer.reference_types.insert(
+        0,
+        ReferenceType {
+            name: None,
+            table: None,
+            namespace: None,
+            span: span.clone(),
+            columns,
+            hidden: Vec::new(),
+            keys: Vec::new(),
+        },
+    );
+}
+
+/// Warn about equalities in a join condition between columns of different
+/// types, that are not related by a foreign key
+fn lint_join_on<'a, 'b>(typer: &mut Typer<'a, 'b>, e: &Expression<'a>) {
+    let (op_span, lhs, rhs) = match e {
+        Expression::Binary {
+            op: BinaryOperator::And,
+            lhs,
+            rhs,
+            ..
+        } => {
+            lint_join_on(typer, lhs);
+            lint_join_on(typer, rhs);
+            return;
+        }
+        Expression::Binary {
+            op: BinaryOperator::Eq,
+            op_span,
+            lhs,
+            rhs,
+        } => (op_span, lhs, rhs),
+        _ => return,
+    };
+    let (l, r) = match (lhs.as_ref(), rhs.as_ref()) {
+        (Expression::Identifier(l), Expression::Identifier(r)) => (l, r),
+        _ => return,
+    };
+    let refs = &typer.reference_types;
+    let ((li, lc), (ri, rc)) = match (
+        resolve_column(typer.options, refs, l),
+        resolve_column(typer.options, refs, r),
+    ) {
+        (Some(l), Some(r)) => (l, r),
+        _ => return,
+    };
+    let (lr, rr) = (&refs[li], &refs[ri]);
+    let lt = lr.columns.iter().find(|c| c.0 == lc).map(|c| &c.1.t);
+    let rt = rr.columns.iter().find(|c| c.0 == rc).map(|c| &c.1.t);
+    if lt == rt {
+        return;
+    }
+    let (lt, rt) = match (lt, rt, lr.table, rr.table) {
+        (Some(lt), Some(rt), Some(ltable), Some(rtable)) => {
+            let schemas = &typer.schemas.schemas;
+            let related = schemas
+                .get(ltable)
+                .is_some_and(|s| s.references(lc, rtable, rc))
+                || schemas
+                    .get(rtable)
+                    .is_some_and(|s| s.references(rc, ltable, lc));
+            if related {
+                return;
+            }
+            (lt.to_string(), rt.to_string())
+        }
+        _ => return,
+    };
+    typer.issues.push(
+        Issue::warn(
+            "Join on columns of different types without a foreign key",
+            op_span,
+        )
+        .frag(format!("Of type {}", lt), lhs)
+        .frag(format!("Of type {}", rt), rhs),
+    );
+}
 
 pub(crate) fn type_reference<'a, 'b>(
     typer: &mut Typer<'a, 'b>,
@@ -29,18 +200,16 @@ pub(crate) fn type_reference<'a, 'b>(
         sql_parse::TableReference::Table {
             identifier, as_, ..
         } => {
-            let identifier = match identifier.as_slice() {
-                [v] => v,
-                _ => {
-                    typer.issues.push(issue_todo!(reference));
-                    typer.reference_types = given_refs;
-                    return;
-                }
-            };
-            if let Some(s) = typer.schemas.schemas.get(&identifier.value) {
+            if let Some(s) = typer.get_schema(identifier) {
+                let namespace = typer.other_namespace(identifier);
+                let table = match namespace {
+                    None => identifier.last().map(|t| t.value),
+                    Some(_) => None,
+                };
+                let identifier = identifier.last().expect("table name");
                 let mut columns = Vec::new();
                 for c in &s.columns {
-                    let mut type_ = c.type_.ref_clone();
+                    let mut type_ = c.type_.clone();
                     type_.not_null = type_.not_null && !force_null;
                     columns.push((c.identifier, type_));
                 }
@@ -53,15 +222,27 @@ pub(crate) fn type_reference<'a, 'b>(
                         );
                     }
                 }
+                let keys = s
+                    .indexes
+                    .iter()
+                    .filter(|i| {
+                        matches!(i.kind, IndexKind::Primary | IndexKind::Unique)
+                            && i.where_.is_none()
+                            && i.columns
+                                .iter()
+                                .all(|c| s.get_column(c).is_some_and(|c| c.type_.not_null))
+                    })
+                    .map(|i| i.columns.clone())
+                    .collect();
                 typer.reference_types.push(ReferenceType {
                     name: Some(name.value),
+                    table,
+                    namespace,
                     span: name.span(),
                     columns,
+                    hidden: Vec::new(),
+                    keys,
                 });
-            } else {
-                typer
-                    .issues
-                    .push(Issue::err("Unknown table or view", identifier))
             }
         }
         sql_parse::TableReference::Query { query, as_, .. } => {
@@ -78,12 +259,16 @@ pub(crate) fn type_reference<'a, 'b>(
 
             typer.reference_types.push(ReferenceType {
                 name,
+                table: None,
+                namespace: None,
                 span,
                 columns: select
                     .columns
                     .iter()
                     .filter_map(|v| v.name.map(|name| (name, v.type_.clone())))
                     .collect(),
+                hidden: Vec::new(),
+                keys: Vec::new(),
             });
         }
         sql_parse::TableReference::Join {
@@ -95,25 +280,55 @@ pub(crate) fn type_reference<'a, 'b>(
             let (left_force_null, right_force_null) = match join {
                 sql_parse::JoinType::Left(_) => (force_null, true),
                 sql_parse::JoinType::Right(_) => (true, force_null),
+                sql_parse::JoinType::NaturalLeft(_) => (force_null, true),
+                sql_parse::JoinType::NaturalRight(_) => (true, force_null),
                 sql_parse::JoinType::Inner(_)
                 | sql_parse::JoinType::Cross(_)
-                | sql_parse::JoinType::Normal(_) => (force_null, force_null),
-                _ => {
-                    typer.issues.push(issue_todo!(join));
-                    (force_null, force_null)
-                }
+                | sql_parse::JoinType::Normal(_)
+                | sql_parse::JoinType::Straight(_)
+                | sql_parse::JoinType::Natural(_)
+                | sql_parse::JoinType::NaturalInner(_) => (force_null, force_null),
             };
             type_reference(typer, left, left_force_null);
+            let left_cnt = typer.reference_types.len();
             type_reference(typer, right, right_force_null);
             match &specification {
                 Some(sql_parse::JoinSpecification::On(e, _)) => {
                     let t = type_expression(typer, e, ExpressionFlags::default(), BaseType::Bool);
                     typer.ensure_base(e, &t, BaseType::Bool);
+                    if typer.options.warn_join_without_foreign_key {
+                        lint_join_on(typer, e);
+                    }
                 }
-                Some(s @ sql_parse::JoinSpecification::Using(_, _)) => {
-                    typer.issues.push(issue_todo!(s));
+                Some(sql_parse::JoinSpecification::Using(cols, span)) => {
+                    type_join_using(typer, join, cols, span, left_cnt);
+                }
+                None => {
+                    if let sql_parse::JoinType::Natural(span)
+                    | sql_parse::JoinType::NaturalInner(span)
+                    | sql_parse::JoinType::NaturalLeft(span)
+                    | sql_parse::JoinType::NaturalRight(span) = join
+                    {
+                        // A natural join is a join using all columns the two sides have in common
+                        let (left_refs, right_refs) = typer.reference_types.split_at(left_cnt);
+                        let mut cols: Vec<Identifier<'a>> = Vec::new();
+                        for r in left_refs {
+                            for c in &r.columns {
+                                if r.hidden.contains(&c.0) || cols.iter().any(|v| v.value == c.0) {
+                                    continue;
+                                }
+                                if right_refs.iter().any(|r| {
+                                    r.columns
+                                        .iter()
+                                        .any(|rc| rc.0 == c.0 && !r.hidden.contains(&rc.0))
+                                }) {
+                                    cols.push(Identifier::new(c.0, span.clone()));
+                                }
+                            }
+                        }
+                        type_join_using(typer, join, &cols, span, left_cnt);
+                    }
                 }
-                None => (),
             }
         }
     }
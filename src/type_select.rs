@@ -19,6 +19,7 @@ use sql_parse::{
 use crate::{
     type_::{BaseType, FullType},
     type_expression::{type_expression, ExpressionFlags},
+    type_group_by::check_group_by,
     type_reference::type_reference,
     typer::{typer_stack, ReferenceType, Typer},
     Type,
@@ -47,6 +48,21 @@ pub(crate) struct SelectType<'a> {
     pub select_span: Span,
 }
 
+impl<'a> SelectType<'a> {
+    /// The result columns, with argument tracking removed from their types
+    pub(crate) fn into_result_columns(self) -> Vec<SelectTypeColumn<'a>> {
+        self.columns
+            .into_iter()
+            .map(|mut c| {
+                if let Type::Args(t, _) = c.type_.t {
+                    c.type_.t = t.into();
+                }
+                c
+            })
+            .collect()
+    }
+}
+
 impl<'a> Spanned for SelectType<'a> {
     fn span(&self) -> Span {
         self.columns
@@ -61,13 +77,13 @@ pub(crate) fn resolve_kleene_identifier<'a, 'b>(
     as_: &Option<Identifier<'a>>,
     mut cb: impl FnMut(Option<&'a str>, FullType<'a>, Span, bool),
 ) {
-    match parts {
+    match typer.strip_namespace(parts) {
         [sql_parse::IdentifierPart::Name(col)] => {
             let mut cnt = 0;
             let mut t = None;
             for r in &typer.reference_types {
                 for c in &r.columns {
-                    if c.0 == col.value {
+                    if c.0 == col.value && !r.hidden.contains(&c.0) {
                         cnt += 1;
                         t = Some(c);
                     }
@@ -78,7 +94,7 @@ pub(crate) fn resolve_kleene_identifier<'a, 'b>(
                 let mut issue = Issue::err("Ambigious reference", col);
                 for r in &typer.reference_types {
                     for c in &r.columns {
-                        if c.0 == col.value {
+                        if c.0 == col.value && !r.hidden.contains(&c.0) {
                             issue = issue.frag("Defined here", &r.span);
                         }
                     }
@@ -108,6 +124,9 @@ pub(crate) fn resolve_kleene_identifier<'a, 'b>(
             }
             for r in &typer.reference_types {
                 for c in &r.columns {
+                    if r.hidden.contains(&c.0) {
+                        continue;
+                    }
                     cb(Some(c.0), c.1.clone(), v.clone(), false);
                 }
             }
@@ -180,8 +199,8 @@ pub(crate) fn type_select<'a, 'b>(
         match &flag {
             sql_parse::SelectFlag::All(_) => typer.issues.push(issue_todo!(flag)),
             sql_parse::SelectFlag::Distinct(_) | sql_parse::SelectFlag::DistinctRow(_) => (),
-            sql_parse::SelectFlag::StraightJoin(_) => typer.issues.push(issue_todo!(flag)),
             sql_parse::SelectFlag::HighPriority(_)
+            | sql_parse::SelectFlag::StraightJoin(_)
             | sql_parse::SelectFlag::SqlSmallResult(_)
             | sql_parse::SelectFlag::SqlBigResult(_)
             | sql_parse::SelectFlag::SqlBufferResult(_)
@@ -190,11 +209,13 @@ pub(crate) fn type_select<'a, 'b>(
         }
     }
 
+    let local_refs = typer.reference_types.len();
     if let Some(references) = &select.table_references {
         for reference in references {
             type_reference(typer, reference, false);
         }
     }
+    let local_refs = local_refs..typer.reference_types.len();
 
     if let Some((where_, _)) = &select.where_ {
         let t = type_expression(
@@ -259,6 +280,11 @@ pub(crate) fn type_select<'a, 'b>(
         }
     }
 
+    if typer.options.only_full_group_by {
+        let refs = typer.reference_types[local_refs].to_vec();
+        check_group_by(typer, select, &refs);
+    }
+
     SelectType {
         columns: result
             .into_iter()
@@ -276,8 +302,12 @@ pub(crate) fn type_select_exprs<'a, 'b>(
     let mut result = Vec::new();
     let mut select_reference = ReferenceType {
         name: None,
+        table: None,
+        namespace: None,
         span: select_exprs.opt_span().expect("select_exprs span"),
         columns: Vec::new(),
+        hidden: Vec::new(),
+        keys: Vec::new(),
     };
 
     let mut add_result_issues = Vec::new();
@@ -318,7 +348,7 @@ pub(crate) fn type_select_exprs<'a, 'b>(
             };
         }
     }
-    typer.issues.extend(add_result_issues.into_iter());
+    typer.issues.extend(add_result_issues);
     typer.reference_types.push(select_reference);
 
     result
@@ -412,12 +442,16 @@ pub(crate) fn type_union<'a, 'b>(typer: &mut Typer<'a, 'b>, union: &Union<'a>) -
 
     typer.reference_types.push(ReferenceType {
         name: None,
+        table: None,
+        namespace: None,
         span: t.span(),
         columns: t
             .columns
             .iter()
             .filter_map(|v| v.name.map(|name| (name, v.type_.clone())))
             .collect(),
+        hidden: Vec::new(),
+        keys: Vec::new(),
     });
 
     if let Some((_, order_by)) = &union.order_by {
@@ -40,13 +40,37 @@ pub(crate) fn type_update<'a, 'b>(typer: &mut Typer<'a, 'b>, update: &Update<'a>
 
     for (key, value) in &update.set {
         let flags = ExpressionFlags::default();
-        match key.as_slice() {
+        let parts = match key.as_slice() {
+            [namespace, rest @ ..] if rest.len() == 2 => {
+                typer.check_qualified_table(namespace, &rest[0]);
+                rest
+            }
+            parts => parts,
+        };
+        let table = match parts {
+            [col] => typer.reference_types.iter().find(|r| {
+                r.columns.iter().any(|c| c.0 == col.value) && !r.hidden.contains(&col.value)
+            }),
+            [tbl, _] => typer
+                .reference_types
+                .iter()
+                .find(|r| r.name == Some(tbl.value)),
+            _ => None,
+        }
+        .and_then(|r| r.table);
+        let schemas = typer.schemas;
+        if let (Some(schema), Some(col)) =
+            (table.and_then(|t| schemas.schemas.get(t)), parts.last())
+        {
+            typer.ensure_writable(schema, col);
+        }
+        match parts {
             [key] => {
                 let mut cnt = 0;
                 let mut t = None;
                 for r in &typer.reference_types {
                     for c in &r.columns {
-                        if c.0 == key.value {
+                        if c.0 == key.value && !r.hidden.contains(&c.0) {
                             cnt += 1;
                             t = Some(c.clone());
                         }
@@ -57,7 +81,7 @@ pub(crate) fn type_update<'a, 'b>(typer: &mut Typer<'a, 'b>, update: &Update<'a>
                     let mut issue = Issue::err("Ambiguous reference", &key.opt_span().unwrap());
                     for r in &typer.reference_types {
                         for c in &r.columns {
-                            if c.0 == key.value {
+                            if c.0 == key.value && !r.hidden.contains(&c.0) {
                                 issue = issue.frag("Defined here", &r.span);
                             }
                         }
@@ -65,14 +89,15 @@ pub(crate) fn type_update<'a, 'b>(typer: &mut Typer<'a, 'b>, update: &Update<'a>
                     typer.issues.push(issue);
                 } else if let Some(t) = t {
                     let value_type = type_expression(typer, value, flags, t.1.base());
+                    typer.ensure_enum_value(value, &t.1.t);
                     if typer.matched_type(&value_type, &t.1).is_none() {
                         typer.issues.push(Issue::err(
                             alloc::format!("Got type {} expected {}", value_type, t.1),
                             value,
                         ));
                     } else if let Type::Args(_, args) = &value_type.t {
-                        for (idx, arg_type, _) in args {
-                            typer.constrain_arg(*idx, arg_type, &t.1);
+                        for (idx, arg_type, span) in args {
+                            typer.constrain_arg(*idx, arg_type, span, &t.1);
                         }
                     }
                 } else {
@@ -96,14 +121,15 @@ pub(crate) fn type_update<'a, 'b>(typer: &mut Typer<'a, 'b>, update: &Update<'a>
                 }
                 if let Some(t) = t {
                     let value_type = type_expression(typer, value, flags, t.1.base());
+                    typer.ensure_enum_value(value, &t.1.t);
                     if typer.matched_type(&value_type, &t.1).is_none() {
                         typer.issues.push(Issue::err(
                             alloc::format!("Got type {} expected {}", value_type, t.1),
                             value,
                         ));
                     } else if let Type::Args(_, args) = &value_type.t {
-                        for (idx, arg_type, _) in args {
-                            typer.constrain_arg(*idx, arg_type, &t.1);
+                        for (idx, arg_type, span) in args {
+                            typer.constrain_arg(*idx, arg_type, span, &t.1);
                         }
                     }
                 } else {
@@ -11,54 +11,406 @@
 // limitations under the License.
 
 use crate::{
-    schema::Schemas,
+    schema::{Schema, Schemas},
     type_::{ArgType, BaseType, FullType},
     ArgumentKey, Type, TypeOptions,
 };
 use alloc::format;
-use alloc::vec::Vec;
-use sql_parse::{Issue, SQLDialect, Span, Spanned};
+use alloc::{collections::BTreeMap, vec::Vec};
+use sql_parse::{
+    Expression, Identifier, IdentifierPart, Issue, OptSpanned, SQLDialect, Span, Spanned,
+};
 
 #[derive(Clone, Debug)]
 pub(crate) struct ReferenceType<'a> {
     pub(crate) name: Option<&'a str>,
+    /// The table in the default namespace the reference is to, if any
+    pub(crate) table: Option<&'a str>,
+    /// The namespace of the table the reference is to, if it is not the default one
+    pub(crate) namespace: Option<&'a str>,
     pub(crate) span: Span,
     pub(crate) columns: Vec<(&'a str, FullType<'a>)>,
+    /// Columns that can only be referenced qualified, like those merged by USING
+    pub(crate) hidden: Vec<&'a str>,
+    /// Sets of columns that identify a row, used for functional dependence
+    pub(crate) keys: Vec<Vec<&'a str>>,
 }
 
 pub(crate) struct Typer<'a, 'b> {
     pub(crate) issues: &'b mut Vec<Issue>,
-    pub(crate) schemas: &'a Schemas<'a>,
+    pub(crate) schemas: &'b Schemas<'a>,
     pub(crate) reference_types: Vec<ReferenceType<'a>>,
-    pub(crate) arg_types: Vec<(ArgumentKey<'a>, FullType<'a>)>,
+    /// The argument, span and type required at each use of an argument
+    pub(crate) arg_uses: Vec<(usize, Span, FullType<'a>)>,
     pub(crate) options: &'b TypeOptions,
 }
 
+/// Does the reference r refer to the table named tbl in namespace
+fn is_qualified_reference(
+    options: &TypeOptions,
+    r: &ReferenceType<'_>,
+    namespace: &str,
+    tbl: &str,
+) -> bool {
+    if r.name != Some(tbl) {
+        return false;
+    }
+    if options.is_default_namespace(namespace) {
+        r.namespace.is_none() && r.table == Some(tbl)
+    } else {
+        r.namespace == Some(namespace)
+    }
+}
+
+/// Find the reference and column a column name resolves to
+pub(crate) fn resolve_column<'a>(
+    options: &TypeOptions,
+    refs: &[ReferenceType<'a>],
+    parts: &[IdentifierPart<'a>],
+) -> Option<(usize, &'a str)> {
+    match parts {
+        [IdentifierPart::Name(namespace), IdentifierPart::Name(tbl), IdentifierPart::Name(col)] => {
+            refs.iter().enumerate().find_map(|(i, r)| {
+                if !is_qualified_reference(options, r, namespace.value, tbl.value) {
+                    return None;
+                }
+                r.columns
+                    .iter()
+                    .find(|c| c.0 == col.value)
+                    .map(|c| (i, c.0))
+            })
+        }
+        [IdentifierPart::Name(col)] => refs.iter().enumerate().find_map(|(i, r)| {
+            r.columns
+                .iter()
+                .find(|c| c.0 == col.value && !r.hidden.contains(&c.0))
+                .map(|c| (i, c.0))
+        }),
+        [IdentifierPart::Name(tbl), IdentifierPart::Name(col)] => {
+            refs.iter().enumerate().find_map(|(i, r)| {
+                if r.name != Some(tbl.value) {
+                    return None;
+                }
+                r.columns
+                    .iter()
+                    .find(|c| c.0 == col.value)
+                    .map(|c| (i, c.0))
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Signedness and number of bits of a precise integer type
+pub(crate) fn int_width(t: &Type<'_>) -> Option<(bool, usize)> {
+    match t {
+        Type::I8 => Some((true, 8)),
+        Type::I16 => Some((true, 16)),
+        Type::I32 => Some((true, 32)),
+        Type::I64 => Some((true, 64)),
+        Type::U8 => Some((false, 8)),
+        Type::U16 => Some((false, 16)),
+        Type::U32 => Some((false, 32)),
+        Type::U64 => Some((false, 64)),
+        _ => None,
+    }
+}
+
+fn int_type<'a>(signed: bool, bits: usize) -> Type<'a> {
+    match (signed, bits) {
+        (true, 8) => Type::I8,
+        (true, 16) => Type::I16,
+        (true, 32) => Type::I32,
+        (true, _) => Type::I64,
+        (false, 8) => Type::U8,
+        (false, 16) => Type::U16,
+        (false, 32) => Type::U32,
+        (false, _) => Type::U64,
+    }
+}
+
+/// Precision and scale of a decimal able to hold all values of t
+pub(crate) fn decimal_digits(t: &Type<'_>) -> Option<(usize, usize)> {
+    match t {
+        Type::Decimal { precision, scale } => Some((*precision, *scale)),
+        t => match int_width(t) {
+            Some((_, 8)) => Some((3, 0)),
+            Some((_, 16)) => Some((5, 0)),
+            Some((_, 32)) => Some((10, 0)),
+            Some((true, _)) => Some((19, 0)),
+            Some((false, _)) => Some((20, 0)),
+            None => None,
+        },
+    }
+}
+
+/// A decimal type with the precision and scale capped at the maximum of the dialect
+pub(crate) fn decimal_type<'a>(dialect: SQLDialect, precision: usize, scale: usize) -> Type<'a> {
+    let (max_precision, max_scale) = if dialect.is_postgresql() {
+        (1000, 1000)
+    } else {
+        (65, 38)
+    };
+    Type::Decimal {
+        precision: precision.min(max_precision),
+        scale: scale.min(max_scale),
+    }
+}
+
+/// The most precise type values of both t1 and t2 can be converted to,
+/// given that they unify to base
+fn common_type<'a>(dialect: SQLDialect, t1: &Type<'a>, t2: &Type<'a>, base: BaseType) -> Type<'a> {
+    match base {
+        BaseType::Integer => match (int_width(t1), int_width(t2)) {
+            (Some((s1, b1)), Some((s2, b2))) => {
+                if s1 == s2 {
+                    int_type(s1, b1.max(b2))
+                } else {
+                    // The signed type must be wider than the unsigned one
+                    let (sb, ub) = if s1 { (b1, b2) } else { (b2, b1) };
+                    int_type(true, sb.max(ub * 2))
+                }
+            }
+            (Some(_), None) => t1.clone(),
+            (None, Some(_)) => t2.clone(),
+            (None, None) => base.into(),
+        },
+        BaseType::Decimal => match (decimal_digits(t1), decimal_digits(t2)) {
+            (Some((p1, s1)), Some((p2, s2))) => {
+                // PostgreSQL allows the scale to exceed the precision
+                let scale = s1.max(s2);
+                let digits = p1.saturating_sub(s1).max(p2.saturating_sub(s2));
+                decimal_type(dialect, digits + scale, scale)
+            }
+            (Some(_), None) if t1.base() == BaseType::Decimal => t1.clone(),
+            (None, Some(_)) if t2.base() == BaseType::Decimal => t2.clone(),
+            _ => base.into(),
+        },
+        BaseType::Float => match (t1, t2) {
+            (Type::F64, _) | (_, Type::F64) => Type::F64,
+            (Type::F32, Type::F32) => Type::F32,
+            (Type::F32, t) | (t, Type::F32) => {
+                // Integer columns and decimals only fit in a double, while
+                // integer literals are converted to the width of the float
+                if matches!(t.base(), BaseType::Float | BaseType::Any)
+                    || *t == Type::Base(BaseType::Integer)
+                {
+                    Type::F32
+                } else {
+                    Type::F64
+                }
+            }
+            _ => base.into(),
+        },
+        _ => base.into(),
+    }
+}
+
 impl<'a, 'b> Typer<'a, 'b> {
     pub(crate) fn dialect(&self) -> SQLDialect {
         self.options.parse_options.get_dialect()
     }
 
-    pub(crate) fn constrain_arg(&mut self, idx: usize, arg_type: &ArgType, t: &FullType<'a>) {
-        // TODO Use arg_type
+    /// Find the tables and views of the given namespace
+    pub(crate) fn namespace(&self, namespace: &str) -> Option<&'b BTreeMap<&'a str, Schema<'a>>> {
+        let schemas = self.schemas;
+        if self.options.is_default_namespace(namespace) {
+            Some(&schemas.schemas)
+        } else {
+            schemas.namespaces.get(namespace)
+        }
+    }
+
+    /// Find the table or view given by a possibly namespace qualified name
+    pub(crate) fn get_schema(&mut self, identifier: &[Identifier<'a>]) -> Option<&'b Schema<'a>> {
+        let schemas = match identifier {
+            [_] => &self.schemas.schemas,
+            [namespace, _] => match self.namespace(namespace.value) {
+                Some(v) => v,
+                None => {
+                    self.issues
+                        .push(Issue::err("Unknown database or schema", namespace));
+                    return None;
+                }
+            },
+            _ => {
+                if let Some(span) = identifier.opt_span() {
+                    self.issues.push(Issue::err("Invalid table name", &span));
+                }
+                return None;
+            }
+        };
+        let name = identifier.last()?;
+        let schema = schemas.get(name.value);
+        if schema.is_none() {
+            self.issues.push(Issue::err("Unknown table or view", name));
+        }
+        schema
+    }
+
+    /// Report an error if column is a generated column of schema
+    pub(crate) fn ensure_writable(&mut self, schema: &Schema<'a>, column: &Identifier<'a>) {
+        if let Some(c) = schema.get_column(column.value) {
+            if c.generated {
+                // The column definition is in the schema source, so it cannot be
+                // pointed at from an issue on the statement
+                self.issues
+                    .push(Issue::err("Cannot write to generated column", column));
+            }
+        }
+    }
+
+    /// Report an error if e is a string literal that is not a value of the enum type t
+    pub(crate) fn ensure_enum_value(&mut self, e: &Expression<'a>, t: &Type<'a>) {
+        if let (Type::Enum(values), Expression::String(s)) = (t, e) {
+            if !values.contains(&s.value) {
+                self.issues.push(Issue::err(
+                    format!("'{}' is not a value of {}", s.value, t),
+                    s,
+                ));
+            }
+        }
+    }
+
+    /// Report an error if no table named tbl in namespace is referenced
+    pub(crate) fn check_qualified_table(
+        &mut self,
+        namespace: &Identifier<'a>,
+        tbl: &Identifier<'a>,
+    ) {
+        if self.namespace(namespace.value).is_none() {
+            self.issues
+                .push(Issue::err("Unknown database or schema", namespace));
+        } else if !self
+            .reference_types
+            .iter()
+            .any(|r| is_qualified_reference(self.options, r, namespace.value, tbl.value))
+        {
+            self.issues.push(Issue::err(
+                format!("Unknown table in {}", namespace.value),
+                tbl,
+            ));
+        }
+    }
+
+    /// Strip the database or schema from a namespace qualified column name
+    pub(crate) fn strip_namespace<'c>(
+        &mut self,
+        parts: &'c [IdentifierPart<'a>],
+    ) -> &'c [IdentifierPart<'a>] {
+        match parts {
+            [IdentifierPart::Name(namespace), rest @ ..] if rest.len() == 2 => {
+                if let IdentifierPart::Name(tbl) = &rest[0] {
+                    self.check_qualified_table(namespace, tbl);
+                }
+                rest
+            }
+            parts => parts,
+        }
+    }
+
+    /// The namespace of a qualified table name, if it is not the default namespace
+    pub(crate) fn other_namespace(&self, identifier: &[Identifier<'a>]) -> Option<&'a str> {
+        match identifier {
+            [namespace, _] if !self.options.is_default_namespace(namespace.value) => {
+                Some(namespace.value)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn constrain_arg(
+        &mut self,
+        idx: usize,
+        arg_type: &ArgType,
+        span: &Span,
+        t: &FullType<'a>,
+    ) {
+        // The last constraint at a use is the most specific one
         let ot = match self
-            .arg_types
+            .arg_uses
             .iter_mut()
-            .find(|(k, _)| k == &ArgumentKey::Index(idx))
+            .find(|(i, s, _)| *i == idx && s == span)
         {
-            Some((_, v)) => v,
+            Some((_, _, v)) => v,
             None => {
-                self.arg_types
-                    .push((ArgumentKey::Index(idx), FullType::new(BaseType::Any, false)));
-                &mut self.arg_types.last_mut().unwrap().1
+                self.arg_uses
+                    .push((idx, span.clone(), FullType::new(BaseType::Any, false)));
+                &mut self.arg_uses.last_mut().unwrap().2
             }
         };
+        let list_hack = ot.list_hack;
         if t.base() != BaseType::Any || ot.base() == BaseType::Any {
             *ot = t.clone();
         }
-        if matches!(arg_type, ArgType::ListHack) {
-            ot.list_hack = true;
+        ot.list_hack = list_hack || matches!(arg_type, ArgType::ListHack);
+    }
+
+    /// Constrain the arguments of the branches to the type t they were unified to
+    pub(crate) fn constrain_branch_args<'c>(
+        &mut self,
+        branches: impl IntoIterator<Item = &'c Type<'a>>,
+        t: &Type<'a>,
+    ) where
+        'a: 'c,
+    {
+        // While the type is not known precisely the arguments are carried on in it
+        if matches!(t, Type::Args(..)) {
+            return;
+        }
+        let t = FullType::new(t.clone(), false);
+        for branch in branches {
+            if let Type::Args(_, args) = branch {
+                for (idx, arg_type, span) in args {
+                    self.constrain_arg(*idx, arg_type, span, &t);
+                }
+            }
+        }
+    }
+
+    /// Unify the constraints of all uses of each argument into its type
+    pub(crate) fn arg_types(&mut self) -> Vec<(ArgumentKey<'a>, FullType<'a>)> {
+        let uses = core::mem::take(&mut self.arg_uses);
+        let mut ans: Vec<(ArgumentKey<'a>, FullType<'a>)> = Vec::new();
+        for (idx, _, _) in &uses {
+            if ans.iter().any(|(k, _)| k == &ArgumentKey::Index(*idx)) {
+                continue;
+            }
+            let mut t = FullType::new(BaseType::Any, true);
+            let mut conflict = false;
+            for (_, _, ut) in uses.iter().filter(|(i, _, _)| i == idx) {
+                // The argument may only be null if all uses allow it
+                t.not_null = t.not_null && ut.not_null;
+                t.list_hack = t.list_hack || ut.list_hack;
+                if ut.base() == BaseType::Any {
+                    continue;
+                }
+                if t.base() == BaseType::Any {
+                    t.t = ut.t.clone();
+                } else if let Some(mt) = self.matched_type(&t, ut) {
+                    t.t = mt;
+                } else {
+                    conflict = true;
+                }
+            }
+            if conflict {
+                let mut issue: Option<Issue> = None;
+                for (_, span, ut) in uses
+                    .iter()
+                    .filter(|(i, _, ut)| i == idx && ut.base() != BaseType::Any)
+                {
+                    issue = Some(match issue {
+                        None => {
+                            Issue::err(format!("Conflicting types for argument {}", idx + 1), span)
+                        }
+                        Some(i) => i.frag(format!("Used as {}", ut.t), span),
+                    });
+                }
+                self.issues.extend(issue);
+            }
+            ans.push((ArgumentKey::Index(*idx), t));
         }
+        ans
     }
 
     pub(crate) fn matched_type(&mut self, t1: &Type<'a>, t2: &Type<'a>) -> Option<Type<'a>> {
@@ -80,18 +432,42 @@ impl<'a, 'b> Typer<'a, 'b> {
         if t2b == BaseType::Any {
             t2b = t1b;
         }
+        // Integers are implicitly converted to decimals and floats, and decimals to floats
+        match (t1b, t2b) {
+            (BaseType::Decimal, BaseType::Integer)
+            | (BaseType::Float, BaseType::Decimal | BaseType::Integer) => t2b = t1b,
+            (BaseType::Integer, BaseType::Decimal | BaseType::Float)
+            | (BaseType::Decimal, BaseType::Float) => t1b = t2b,
+            // Dates are implicitly converted to date times, and date times to timestamps
+            (
+                BaseType::Date | BaseType::DateTime | BaseType::TimeStamp,
+                BaseType::Date | BaseType::DateTime | BaseType::TimeStamp,
+            ) if t1b != t2b => {
+                let t = if t1b == BaseType::TimeStamp || t2b == BaseType::TimeStamp {
+                    BaseType::TimeStamp
+                } else {
+                    BaseType::DateTime
+                };
+                t1b = t;
+                t2b = t;
+            }
+            _ => (),
+        }
         if t1b != t2b {
             return None;
         }
 
-        for t in &[t1, t2] {
-            if let Type::Args(_, a) = t {
-                for (idx, arg_type, _) in a {
-                    self.constrain_arg(*idx, arg_type, &FullType::new(t1b, false));
+        let t = common_type(self.dialect(), t1, t2, t1b);
+        for t1 in &[t1, t2] {
+            if let Type::Args(_, a) = t1 {
+                for (idx, arg_type, span) in a {
+                    self.constrain_arg(*idx, arg_type, span, &FullType::new(t.clone(), false));
                 }
             }
         }
-        if t1b == BaseType::Any {
+        // Keep track of arguments until their type is known precisely, so it can
+        // be refined by later constraints
+        if matches!(t, Type::Base(_)) {
             let mut args = Vec::new();
             for t in &[t1, t2] {
                 if let Type::Args(_, a) = t {
@@ -102,7 +478,7 @@ impl<'a, 'b> Typer<'a, 'b> {
                 return Some(Type::Args(t1b, args));
             }
         }
-        Some(t1b.into())
+        Some(t)
     }
 
     pub(crate) fn ensure_type(